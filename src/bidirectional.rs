@@ -0,0 +1,639 @@
+use std::f64::consts::PI;
+use crate::color::Color;
+use crate::lights::{Light, LightShape};
+use crate::materials::{Material, ShadingModel};
+use crate::object::{Intersectable, Object, RayKind};
+use crate::ray::Ray;
+use crate::sampling::{from_local, Rng};
+use crate::tuple::Tuple;
+use crate::world::{refract, World};
+use crate::EPSILON;
+
+#[derive(Debug, Clone)]
+enum VertexKind {
+    Camera,
+    Light(Light),
+    // the start of a light subpath on an emissive object, with its radiance
+    Emitter(Color),
+    Surface {
+        object: Box<Object>,
+        material: Box<Material>,
+        // position in World::objects
+        object_id: Option<usize>,
+        // given off back along the path, black when the surface was reached from inside
+        emission: Color
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Vertex {
+    kind: VertexKind,
+    point: Tuple,
+    // None for the camera and point lights
+    normal: Option<Tuple>,
+    // throughput of the subpath up to and including the sampling of this vertex
+    beta: Color,
+    // the subpath left this vertex along a mirror or refracted direction
+    delta: bool,
+    // area densities of reaching this vertex from the previous vertex of its subpath and from the next one
+    pdf_fwd: f64,
+    pdf_rev: f64
+}
+
+// what light subpaths start from, picked uniformly
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Source<'a> {
+    Light(&'a Light),
+    // an emissive sphere, cube or triangle and its position in World::objects
+    Object(usize, &'a Object)
+}
+
+pub(crate) fn light_sources(world: &World) -> Vec<Source<'_>> {
+    let lights = world.lights.iter().map(Source::Light);
+    let emissive = world.objects.iter().enumerate()
+        .filter(|(_, object)| object.material_ref().emission != Color::black() && local_area(object).is_some())
+        .map(|(id, object)| Source::Object(id, object));
+    lights.chain(emissive).collect()
+}
+
+// area of the untransformed surface, None for shapes that can't be sampled as lights
+fn local_area(object: &Object) -> Option<f64> {
+    match *object {
+        // local_intersect treats the sphere's radius field as the squared radius
+        Object::Sphere(ref sphere) => Some(4. * PI * sphere.radius),
+        Object::Cube(_) => Some(24.),
+        Object::Triangle(ref triangle) => Some(triangle.e1.cross(triangle.e2).length() / 2.),
+        _ => None
+    }
+}
+
+// spread evenly over the untransformed surface
+fn sample_local(object: &Object, rng: &mut Rng) -> Tuple {
+    match *object {
+        Object::Sphere(ref sphere) => sphere.origin + sphere_direction(rng) * sphere.radius.sqrt(),
+        Object::Cube(_) => {
+            let face = ((rng.next_f64() * 6.) as usize).min(5);
+            let side = if face.is_multiple_of(2) { 1. } else { -1. };
+            let (a, b) = (2. * rng.next_f64() - 1., 2. * rng.next_f64() - 1.);
+            match face / 2 {
+                0 => Tuple::point(side, a, b),
+                1 => Tuple::point(a, side, b),
+                _ => Tuple::point(a, b, side)
+            }
+        }
+        Object::Triangle(ref triangle) => {
+            let root = rng.next_f64().sqrt();
+            let v = root * rng.next_f64();
+            triangle.p1 + triangle.e1 * (root - v) + triangle.e2 * v
+        }
+        _ => panic!("Invalid args. Only spheres, cubes and triangles can be sampled")
+    }
+}
+
+// density per unit of world area of the points sample_local spreads over the transformed surface
+fn area_pdf(object: &Object, world_point: Tuple) -> f64 {
+    let area = match local_area(object) {
+        Some(area) => area,
+        None => return 0.
+    };
+    let transform = object.transform();
+    let inverse = transform.inverse();
+    let local_normal = object.local_normal_at(inverse * world_point).normalize();
+    let mut stretched = inverse.transpose() * local_normal;
+    stretched.w = 0.;
+    // a local patch dA covers |det M| |M^-T n| dA of the world
+    1. / (area * transform.determinant().abs() * stretched.length())
+}
+
+fn cosine_direction(normal: Tuple, rng: &mut Rng) -> Tuple {
+    let radius = rng.next_f64().sqrt();
+    let phi = 2. * PI * rng.next_f64();
+    from_local(normal, radius * phi.cos(), radius * phi.sin(), (1. - radius * radius).sqrt())
+}
+
+fn sphere_direction(rng: &mut Rng) -> Tuple {
+    let z = 1. - 2. * rng.next_f64();
+    let radius = (1. - z * z).sqrt();
+    let phi = 2. * PI * rng.next_f64();
+    Tuple::vector(radius * phi.cos(), radius * phi.sin(), z)
+}
+
+// chances of leaving a surface along the brdf, the mirror direction or the refracted one. They ignore the
+// incoming direction so both subpaths agree on them, fresnel only scales what the chosen lobe carries
+fn lobe_chances(material: &Material) -> (f64, f64, f64) {
+    let brdf = if material.model == ShadingModel::Microfacet || material.diffuse > 0. || material.specular > 0. { 1. } else { 0. };
+    let total = brdf + material.reflective + material.transparency;
+    if total <= 0. {
+        return (0., 0., 0.);
+    }
+    (brdf / total, material.reflective / total, material.transparency / total)
+}
+
+// (forward, reverse, connectable) per vertex, a vertex can only be joined to when neither it nor the one
+// before it left along a perfect specular direction
+fn strategies(path: &[Vertex]) -> Vec<(f64, f64, bool)> {
+    path.iter().enumerate()
+        .map(|(i, vertex)| (vertex.pdf_fwd, vertex.pdf_rev, i > 0 && !vertex.delta && !path[i - 1].delta))
+        .collect()
+}
+
+// power heuristic terms of the strategies that would have sampled these vertices from the other side,
+// walking away from the connection
+fn ratio_sum(pdfs: &[(f64, f64, bool)]) -> f64 {
+    let remap = |pdf: f64| if pdf == 0. { 1. } else { pdf };
+    let mut sum = 0.;
+    let mut r = 1.;
+    for &(fwd, rev, connectable) in pdfs.iter().rev() {
+        r *= remap(rev) / remap(fwd);
+        if connectable {
+            sum += r * r;
+        }
+    }
+    sum
+}
+
+impl Vertex {
+    // turns a solid angle density of leaving this vertex into an area density at the next one
+    fn to_area(&self, pdf: f64, next: &Vertex) -> f64 {
+        let w = next.point - self.point;
+        let distance_squared = w.dot(w);
+        let cos = next.normal.map_or(1., |normal| normal.dot(w).abs() / distance_squared.sqrt());
+        pdf * cos / distance_squared
+    }
+
+    // solid angle density of emitting or scattering towards the point, the same whichever way light arrived
+    fn pdf_towards(&self, point: Tuple) -> f64 {
+        let direction = (point - self.point).normalize();
+        match self.kind {
+            VertexKind::Camera => 0.,
+            VertexKind::Light(ref light) if light.shape == LightShape::Point => 1. / (4. * PI),
+            VertexKind::Surface { ref material, .. } => lobe_chances(material).0 * self.normal.map_or(0., |normal| normal.dot(direction).max(0.) / PI),
+            _ => self.normal.map_or(0., |normal| normal.dot(direction).max(0.) / PI)
+        }
+    }
+
+    fn is_point_light(&self) -> bool {
+        matches!(self.kind, VertexKind::Light(ref light) if light.shape == LightShape::Point)
+    }
+
+    // the brdf at a surface or the emitted radiance at a light, previous is the other neighbour on the path
+    fn f(&self, previous: Tuple, towards: Tuple) -> Color {
+        let direction = (towards - self.point).normalize();
+        let front = self.normal.is_none_or(|normal| normal.dot(direction) > 0.);
+        match self.kind {
+            VertexKind::Camera => Color::black(),
+            // shade_hit lights a lambertian surface with pi times the intensity of a point light
+            VertexKind::Light(ref light) if light.shape == LightShape::Point => light.intensity * PI,
+            VertexKind::Light(ref light) if front => light.intensity,
+            VertexKind::Emitter(radiance) if front => radiance,
+            VertexKind::Light(_) | VertexKind::Emitter(_) => Color::black(),
+            VertexKind::Surface { ref object, ref material, .. } => {
+                let normal = self.normal.unwrap();
+                material.brdf(object, self.point, direction, (previous - self.point).normalize(), normal)
+            }
+        }
+    }
+}
+
+// connects subpaths grown from the camera and from the lights, weighting every way of building a path with the
+// power heuristic. Emissive spheres, cubes and triangles start light subpaths as well as being hit by camera ones,
+// emissive shapes of other kinds are only found by the camera. Point lights follow shade_hit and don't fall off
+// with distance. Surfaces scatter through their brdf and follow their reflective and transparency weights as a
+// perfect mirror and refraction. Roughness, conductors and coats are left out, and glass passes light by
+// refracting it rather than through transparent shadows
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Bidirectional {
+    // surfaces a path may bounce off between the light and the camera
+    pub max_depth: usize,
+    pub samples: usize
+}
+
+impl Default for Bidirectional {
+    fn default() -> Self {
+        Bidirectional { max_depth: 5, samples: 16 }
+    }
+}
+
+impl Bidirectional {
+    pub fn new(max_depth: usize, samples: usize) -> Self {
+        if max_depth == 0 || samples == 0 {
+            panic!("Invalid args. Depth and samples must be at least one")
+        }
+        Bidirectional { max_depth, samples }
+    }
+
+    pub fn radiance(&self, world: &World, ray: Ray, rng: &mut Rng) -> Color {
+        self.radiance_from(world, &light_sources(world), ray, rng)
+    }
+
+    // sources are gathered once per render rather than per sample
+    pub(crate) fn radiance_from(&self, world: &World, sources: &[Source], ray: Ray, rng: &mut Rng) -> Color {
+        let camera = self.camera_path(world, ray, rng);
+        let light = self.light_path(world, sources, rng);
+        let mut total = Color::black();
+        for t in 2..=camera.len() {
+            // the camera path ran into something emissive
+            if let VertexKind::Surface { emission, .. } = camera[t - 1].kind {
+                if emission != Color::black() {
+                    total = total + camera[t - 1].beta * emission * Bidirectional::emission_weight(sources, &camera, t);
+                }
+            }
+            for s in 1..=light.len() {
+                if s + t - 2 > self.max_depth {
+                    break;
+                }
+                total = total + self.connect(world, &light, &camera, s, t);
+            }
+        }
+        total
+    }
+
+    // one vertex longer than the light path, so an emitter can still be hit after the last bounce
+    fn camera_path(&self, world: &World, ray: Ray, rng: &mut Rng) -> Vec<Vertex> {
+        let camera = Vertex {
+            kind: VertexKind::Camera,
+            point: ray.origin,
+            normal: None,
+            beta: Color::white(),
+            delta: false,
+            pdf_fwd: 1.,
+            pdf_rev: 0.
+        };
+        let mut path = vec![camera];
+        self.walk(world, ray, RayKind::Camera, Color::white(), 1., rng, &mut path, self.max_depth + 2);
+        path
+    }
+
+    fn light_path(&self, world: &World, sources: &[Source], rng: &mut Rng) -> Vec<Vertex> {
+        if sources.is_empty() {
+            return vec![];
+        }
+        let count = sources.len();
+        let pdf_source = 1. / count as f64;
+
+        let (kind, point, normal, pdf_position, radiance) = match sources[((rng.next_f64() * count as f64) as usize).min(count - 1)] {
+            Source::Light(light) => match light.normal() {
+                None => (VertexKind::Light(light.clone()), light.position, None, 1., light.intensity * PI),
+                Some(normal) => {
+                    let point = light.sample_point(rng.next_f64(), rng.next_f64()) + normal * EPSILON;
+                    (VertexKind::Light(light.clone()), point, Some(normal), 1. / light.area(), light.intensity)
+                }
+            },
+            Source::Object(_, object) => {
+                let point = object.transform() * sample_local(object, rng);
+                let normal = object.normal_at(point);
+                let emission = object.material_ref().emission;
+                (VertexKind::Emitter(emission), point + normal * EPSILON, Some(normal), area_pdf(object, point), emission)
+            }
+        };
+        let (direction, pdf_direction, cos) = match normal {
+            None => (sphere_direction(rng), 1. / (4. * PI), 1.),
+            Some(normal) => {
+                let direction = cosine_direction(normal, rng);
+                let cos = direction.dot(normal);
+                (direction, cos / PI, cos)
+            }
+        };
+        let origin = Vertex {
+            kind,
+            point,
+            normal,
+            beta: Color::white() * (1. / (pdf_source * pdf_position)),
+            delta: false,
+            pdf_fwd: pdf_source * pdf_position,
+            pdf_rev: 0.
+        };
+        let mut path = vec![origin];
+        if pdf_direction <= 0. {
+            return path;
+        }
+        let beta = radiance * (cos / (pdf_source * pdf_position * pdf_direction));
+        self.walk(world, Ray::new(point, direction), RayKind::Reflection, beta, pdf_direction, rng, &mut path, self.max_depth + 1);
+        if path[0].is_point_light() && path.len() > 1 {
+            // undo the squared distance the first hit was sampled with, the rest of the subpath carries it along
+            let w = path[1].point - path[0].point;
+            let distance_squared = w.dot(w);
+            for vertex in path[1..].iter_mut() {
+                vertex.beta = vertex.beta * distance_squared;
+            }
+        }
+        path
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn walk(&self, world: &World, mut ray: Ray, mut kind: RayKind, mut beta: Color, mut pdf_direction: f64, rng: &mut Rng, path: &mut Vec<Vertex>, max_vertices: usize) {
+        while path.len() < max_vertices {
+            let xs = world.intersect_visible(ray, kind);
+            let hit = match xs.hit() {
+                Some(hit) => hit,
+                None => return
+            };
+            let comps = hit.prepare_computations(ray, &xs);
+            let mut vertex = Vertex {
+                kind: VertexKind::Surface {
+                    object: Box::new(comps.object.clone()),
+                    material: Box::new(comps.material.clone().into_owned()),
+                    object_id: comps.object_id,
+                    emission: if comps.inside { Color::black() } else { comps.material.emission }
+                },
+                point: comps.over_point,
+                normal: Some(comps.normal_v),
+                beta,
+                delta: false,
+                pdf_fwd: 0.,
+                pdf_rev: 0.
+            };
+            vertex.pdf_fwd = path.last().unwrap().to_area(pdf_direction, &vertex);
+            path.push(vertex);
+            if path.len() == max_vertices {
+                return;
+            }
+
+            let (brdf, mirror, refraction) = lobe_chances(&comps.material);
+            let lobe = rng.next_f64();
+            let n = path.len();
+            if lobe < brdf {
+                let direction = cosine_direction(comps.normal_v, rng);
+                pdf_direction = brdf * direction.dot(comps.normal_v) / PI;
+                let f = comps.material.brdf(&comps.object, comps.over_point, direction, comps.eye_v, comps.normal_v);
+                if pdf_direction <= 0. || f == Color::black() {
+                    return;
+                }
+                beta = beta * f * (direction.dot(comps.normal_v) / pdf_direction);
+
+                let reverse = path[n - 1].pdf_towards(path[n - 2].point);
+                path[n - 2].pdf_rev = path[n - 1].to_area(reverse, &path[n - 2]);
+                ray = comps.spawn_ray(comps.over_point, direction);
+                kind = RayKind::Reflection;
+                continue;
+            }
+
+            // the same weights reflected_color and refracted_color give the two perfect directions
+            let material = &comps.material;
+            let (reflected, refracted) = if material.reflective > 0. && material.transparency > 0. {
+                let reflectance = comps.reflectance();
+                (reflectance * material.reflective, (Color::white() - reflectance) * material.transparency)
+            } else {
+                (Color::white() * material.reflective, Color::white() * material.transparency)
+            };
+            if lobe < brdf + mirror {
+                beta = beta * reflected * (1. / mirror);
+                ray = comps.spawn_ray(comps.over_point, comps.reflect_v);
+                kind = RayKind::Reflection;
+            } else {
+                let direction = match refract(comps.eye_v, comps.normal_v, comps.n1 / comps.n2) {
+                    Some(direction) => direction,
+                    None => return
+                };
+                beta = beta * refracted * (1. / refraction);
+                ray = comps.spawn_ray(comps.under_point, direction);
+                kind = RayKind::Refraction;
+            }
+            path[n - 1].delta = true;
+            path[n - 2].pdf_rev = 0.;
+            pdf_direction = 0.;
+        }
+    }
+
+    fn visible(world: &World, from: Tuple, to: Tuple) -> bool {
+        let w = to - from;
+        let distance = w.length();
+        let xs = world.intersect_visible(Ray::new(from, w.normalize()), RayKind::Shadow);
+        !xs.data.iter().any(|hit| hit.t > EPSILON && hit.t < distance - EPSILON)
+    }
+
+    // the first s light vertices joined to the first t camera vertices
+    fn connect(&self, world: &World, light: &[Vertex], camera: &[Vertex], s: usize, t: usize) -> Color {
+        let a = &light[s - 1];
+        let b = &camera[t - 1];
+        let f_a = a.f(if s > 1 { light[s - 2].point } else { a.point }, b.point);
+        let f_b = b.f(camera[t - 2].point, a.point);
+        if f_a == Color::black() || f_b == Color::black() || !Bidirectional::visible(world, a.point, b.point) {
+            return Color::black();
+        }
+        if let (VertexKind::Light(ref source), VertexKind::Surface { ref object, object_id, .. }) = (&a.kind, &b.kind) {
+            if !source.illuminates(*object_id, object.name()) {
+                return Color::black();
+            }
+        }
+
+        let w = b.point - a.point;
+        let distance_squared = w.dot(w);
+        let direction = w.normalize();
+        let cos_a = a.normal.map_or(1., |normal| normal.dot(direction).abs());
+        let cos_b = b.normal.map_or(1., |normal| normal.dot(direction).abs());
+        let geometry = if a.is_point_light() { cos_b } else { cos_a * cos_b / distance_squared };
+        a.beta * f_a * f_b * b.beta * (geometry * Bidirectional::mis_weight(light, camera, s, t))
+    }
+
+    fn mis_weight(light: &[Vertex], camera: &[Vertex], s: usize, t: usize) -> f64 {
+        let a = &light[s - 1];
+        let b = &camera[t - 1];
+        // the reverse densities of the vertices around the connection as if the path had been grown through it,
+        // the two joined vertices scatter through their brdfs whichever way their own subpaths went on
+        let mut camera_pdfs = strategies(&camera[..t]);
+        let mut light_pdfs = strategies(&light[..s]);
+        camera_pdfs[t - 1].2 = !camera[t - 2].delta;
+        if s > 1 {
+            light_pdfs[s - 1].2 = !light[s - 2].delta;
+        }
+        // only emissive objects can also be reached by the camera path running into them
+        light_pdfs[0].2 = matches!(light[0].kind, VertexKind::Emitter(_));
+        camera_pdfs[t - 1].1 = a.to_area(a.pdf_towards(b.point), b);
+        if t > 2 {
+            camera_pdfs[t - 2].1 = b.to_area(b.pdf_towards(camera[t - 2].point), &camera[t - 2]);
+        }
+        light_pdfs[s - 1].1 = b.to_area(b.pdf_towards(a.point), a);
+        if s > 1 {
+            light_pdfs[s - 2].1 = a.to_area(a.pdf_towards(light[s - 2].point), &light[s - 2]);
+        }
+
+        1. / (1. + ratio_sum(&camera_pdfs[2..t]) + ratio_sum(&light_pdfs))
+    }
+
+    // the camera path hit emission at vertex t - 1, weighed against the light subpaths that could start there
+    fn emission_weight(sources: &[Source], camera: &[Vertex], t: usize) -> f64 {
+        let hit = &camera[t - 1];
+        let (object, object_id) = match hit.kind {
+            VertexKind::Surface { ref object, object_id, .. } => (object, object_id),
+            _ => return 1.
+        };
+        if !sources.iter().any(|source| matches!(*source, Source::Object(id, _) if Some(id) == object_id)) {
+            return 1.;
+        }
+
+        let mut camera_pdfs = strategies(&camera[..t]);
+        camera_pdfs[t - 1].2 = !camera[t - 2].delta;
+        camera_pdfs[t - 1].1 = area_pdf(object, hit.point) / sources.len() as f64;
+        if t > 2 {
+            camera_pdfs[t - 2].1 = hit.to_area(hit.pdf_towards(camera[t - 2].point), &camera[t - 2]);
+        }
+        1. / (1. + ratio_sum(&camera_pdfs[2..t]))
+    }
+}
+
+#[cfg(test)]
+mod tests_bidirectional {
+    use crate::matrix::Matrix;
+    use crate::plane::Plane;
+    use crate::sphere::Sphere;
+    use crate::triangle::Triangle;
+    use super::*;
+
+    fn diffuse(color: Color) -> Material {
+        Material { color, ambient: 0., diffuse: 1., specular: 0., ..Material::phong() }
+    }
+
+    fn floor() -> Object {
+        Object::from(Plane { material: diffuse(Color::white() * 0.5), ..Plane::default() })
+    }
+
+    fn average(integrator: Bidirectional, world: &World, ray: Ray, samples: usize) -> Color {
+        let mut rng = Rng::new(7);
+        let total = (0..samples).fold(Color::black(), |total, _| total + integrator.radiance(world, ray, &mut rng));
+        total * (1. / samples as f64)
+    }
+
+    fn looking_down() -> Ray {
+        Ray::new(Tuple::point(0.3, 1., 0.2), Tuple::vector(0., -1., 0.))
+    }
+
+    fn close(a: Color, b: Color, tolerance: f64) -> bool {
+        (a.red - b.red).abs() < tolerance && (a.green - b.green).abs() < tolerance && (a.blue - b.blue).abs() < tolerance
+    }
+
+    #[test]
+    fn a_point_light_lights_a_surface_like_shade_hit() {
+        let world = World::new(vec![floor()], vec![Light::point_light(Tuple::point(1.3, 2., 0.2), Color::new(1., 0.5, 0.25))]);
+        let color = average(Bidirectional::new(1, 1), &world, looking_down(), 1);
+
+        assert!(close(color, world.color_at(looking_down(), 5), 1e-9));
+        assert!(close(color, Color::new(1., 0.5, 0.25) * (0.5 * 2. / 5f64.sqrt()), 1e-4));
+    }
+
+    #[test]
+    fn mirrors_and_clear_panes_are_followed_like_color_at() {
+        let light = Light::point_light(Tuple::point(1., 1., 3.), Color::white());
+        let mut mirror = Plane { material: Material { reflective: 1., ..diffuse(Color::black()) }, ..Plane::default() };
+        mirror.material.diffuse = 0.;
+        mirror.set_transform(Matrix::translation(Tuple::vector(0., 2., 0.)));
+        let mut pane = Plane { material: Material { transparency: 1., ..mirror.material.clone() }, ..Plane::default() };
+        pane.material.reflective = 0.;
+        pane.set_transform(Matrix::translation(Tuple::vector(0., 1.5, 0.)));
+        let mirrored = World::new(vec![floor(), Object::from(mirror)], vec![light.clone()]);
+        let behind_glass = World::new(vec![floor(), Object::from(pane)], vec![light]);
+        let up = Ray::new(Tuple::point(0., 1., 0.), Tuple::vector(0., 1., 1.).normalize());
+        let down = Ray::new(Tuple::point(0., 3., 1.), Tuple::vector(0., -1., 0.));
+
+        let reflected = average(Bidirectional::new(2, 1), &mirrored, up, 1);
+        let refracted = average(Bidirectional::new(2, 1), &behind_glass, down, 1);
+
+        assert!(reflected.red > 0.);
+        assert!(close(reflected, mirrored.color_at(up, 5), 1e-9));
+        assert!(close(refracted, behind_glass.color_at(down, 5), 1e-9));
+    }
+
+    #[test]
+    fn an_area_light_lights_like_its_area_times_radiance() {
+        let light = Light::area_light(Tuple::point(0.3, 4., 0.2), Tuple::vector(0.2, 0., 0.), Tuple::vector(0., 0., 0.2), Color::white());
+        let world = World::new(vec![floor()], vec![light]);
+        let color = average(Bidirectional::new(1, 1), &world, looking_down(), 64);
+
+        assert_eq!(world.lights[0].normal(), Some(Tuple::vector(0., -1., 0.)));
+        assert!(close(color, Color::white() * (0.5 / PI * 0.04 / 16.), 1e-4));
+    }
+
+    #[test]
+    fn emissive_surfaces_are_seen_directly() {
+        let glow = Sphere { material: Material { emission: Color::new(0.8, 0.4, 0.2), ..diffuse(Color::black()) }, ..Sphere::default() };
+        let world = World::new(vec![Object::from(glow)], vec![]);
+        let ray = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+
+        assert!(close(average(Bidirectional::new(3, 1), &world, ray, 1), Color::new(0.8, 0.4, 0.2), 1e-9));
+    }
+
+    #[test]
+    fn light_reaching_a_shadow_through_a_bounce_needs_the_depth() {
+        let mut wall = Plane { material: diffuse(Color::white()), ..Plane::default() };
+        wall.set_transform(Matrix::translation(Tuple::vector(0., 3., 0.)));
+        let mut blocker = Sphere { material: diffuse(Color::black()), ..Sphere::default() };
+        blocker.set_transform(Matrix::translation(Tuple::vector(0.3, 1.5, 0.2)));
+        let world = World::new(
+            vec![floor(), Object::from(wall), Object::from(blocker)],
+            vec![Light::point_light(Tuple::point(0.3, 2.9, 0.2), Color::white() * 10.)]
+        );
+        let ray = Ray::new(Tuple::point(4., 1., 0.2), Tuple::vector(-3.7, -1., 0.).normalize());
+
+        let direct = average(Bidirectional::new(1, 1), &world, ray, 32);
+        let bounced = average(Bidirectional::new(2, 1), &world, ray, 32);
+
+        assert_eq!(direct, Color::black());
+        assert!(bounced.red > 0.);
+    }
+
+    #[test]
+    fn the_strategies_add_up_inside_a_closed_sphere() {
+        let shell = Sphere { material: diffuse(Color::white() * 0.5), ..Sphere::default() };
+        let world = World::new(vec![Object::from(shell)], vec![Light::point_light(Tuple::point(0., 0., 0.), Color::white())]);
+        let ray = Ray::new(Tuple::point(0.2, 0.1, 0.), Tuple::vector(0., 0., 1.));
+        let color = average(Bidirectional::new(3, 1), &world, ray, 1000);
+
+        // every bounce off a uniformly lit sphere scales its radiance by the albedo
+        assert!(close(color, Color::white() * (0.5 * 1.75), 0.03));
+    }
+
+    #[test]
+    fn mirror_bounces_add_up_inside_a_half_mirrored_sphere() {
+        let shell = Sphere { material: Material { reflective: 0.5, ..diffuse(Color::white() * 0.5) }, ..Sphere::default() };
+        let world = World::new(vec![Object::from(shell)], vec![Light::point_light(Tuple::point(0., 0., 0.), Color::white())]);
+        let ray = Ray::new(Tuple::point(0.2, 0.1, 0.), Tuple::vector(0., 0., 1.));
+        let color = average(Bidirectional::new(2, 1), &world, ray, 2000);
+
+        // the direct light plus one bounce that keeps all of it, half diffusely and half in the mirror. Deeper
+        // paths would add the point light's reflection in the mirror, which color_at never sees
+        assert!(close(color, Color::white(), 0.03));
+    }
+
+    // a square of two triangles centred over the looking_down point, facing the floor
+    fn emissive_panel(size: f64, height: f64, emission: Color) -> Vec<Object> {
+        let corner = |x: f64, z: f64| Tuple::point(0.3 + x * size / 2., height, 0.2 + z * size / 2.);
+        let material = Material { emission, ..diffuse(Color::black()) };
+        vec![
+            Triangle::new(corner(-1., -1.), corner(-1., 1.), corner(1., 1.)),
+            Triangle::new(corner(-1., -1.), corner(1., 1.), corner(1., -1.))
+        ]
+        .into_iter()
+        .map(|triangle| Object::from(Triangle { material: material.clone(), ..triangle }))
+        .collect()
+    }
+
+    #[test]
+    fn surface_densities_follow_the_transform() {
+        let mut sphere = Sphere::default();
+        sphere.set_transform(Matrix::translation(Tuple::vector(1., 0., 0.)) * Matrix::scaling(Tuple::vector(2., 2., 2.)));
+        let panel = emissive_panel(0.4, 2., Color::white());
+
+        assert!((area_pdf(&Object::from(sphere), Tuple::point(3., 0., 0.)) - 1. / (16. * PI)).abs() < 1e-9);
+        assert!((area_pdf(&panel[0], Tuple::point(0.3, 2., 0.2)) - 1. / 0.08).abs() < 1e-9);
+        assert_eq!(panel[0].normal_at(Tuple::point(0.3, 2., 0.2)), Tuple::vector(0., -1., 0.));
+        assert_eq!(area_pdf(&floor(), Tuple::point(0., 0., 0.)), 0.);
+    }
+
+    #[test]
+    fn an_emissive_panel_lights_like_an_area_light_of_its_size() {
+        let mut wall = Plane { material: diffuse(Color::white()), ..Plane::default() };
+        wall.set_transform(Matrix::translation(Tuple::vector(0., 0., 2.)) * Matrix::rotation_x(PI / 2.));
+        let panel = Light::area_light(Tuple::point(0.3, 2., 0.2), Tuple::vector(0.4, 0., 0.), Tuple::vector(0., 0., 0.4), Color::white());
+        let lit = World::new(vec![floor(), Object::from(wall.clone())], vec![panel]);
+        let mut objects = vec![floor(), Object::from(wall)];
+        objects.extend(emissive_panel(0.4, 2., Color::white()));
+        let glowing = World::new(objects, vec![]);
+        let integrator = Bidirectional::new(2, 1);
+
+        let expected = average(integrator, &lit, looking_down(), 4000);
+        let found = average(integrator, &glowing, looking_down(), 4000);
+
+        assert!(light_sources(&glowing).len() == 2);
+        assert!(close(found, expected, expected.red * 0.05));
+    }
+}
@@ -21,7 +21,7 @@ fn main() {
     material.pattern = Option::from(Patterns::from(pattern1));
 
     let mut floor = Plane::default();
-    floor.set_material(material);
+    floor.set_material(material.clone());
 
     let mut wall = Plane::default();
     wall.set_transform(Matrix::translation(Tuple::vector(0., 0., 10.)) * Matrix::rotation_x(PI/2.));
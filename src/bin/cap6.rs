@@ -50,7 +50,7 @@ fn main() {
                 let normal = hit.object.normal_at(point);
                 let eye = -ray.direction;
 
-                let color = hit.object.material().lighting(hit.object, light, point, eye, normal, true);
+                let color = hit.object.material().lighting(&hit.object, &light, point, eye, normal, true);
 
                 let mut canvas = canvas_mutex.lock().unwrap();
                 canvas.set_pixel_color(x, y, color);
@@ -22,13 +22,13 @@ fn main() {
     left_wall.transform = Matrix::translation(Tuple::vector(0., 0., 5.)) *
         Matrix::rotation_y(-PI/4.) * Matrix::rotation_x(PI/2.) *
         Matrix::scaling(Tuple::vector(10., 0.01, 10.));
-    left_wall.material = floor.material;
+    left_wall.material = floor.material.clone();
 
     let mut right_wall = Sphere::default();
     right_wall.transform = Matrix::translation(Tuple::vector(0., 0., 5.)) *
         Matrix::rotation_y(PI/4.) * Matrix::rotation_x(PI/2.) *
         Matrix::scaling(Tuple::vector(10., 0.01, 10.));
-    right_wall.material = floor.material;
+    right_wall.material = floor.material.clone();
 
     let mut middle = Sphere::default();
     middle.transform = Matrix::translation(Tuple::vector(-0.5, 1., 0.5));
@@ -1,10 +1,17 @@
 use std::sync::{Mutex};
 use itertools::Itertools;
 use rayon::prelude::*;
+use crate::bidirectional::{light_sources, Bidirectional};
 use crate::canvas::Canvas;
+use crate::color::Color;
 use crate::equivalent::Equivalence;
 use crate::matrix::Matrix;
+use crate::object::{Intersectable, RayKind};
+use crate::occlusion::AmbientOcclusion;
+use crate::outline::{GeometryBuffer, Surface};
 use crate::ray::Ray;
+use crate::sampling::Rng;
+use crate::spectrum::{stratified_wavelength, Spectrum};
 use crate::tuple::Tuple;
 use crate::world::World;
 
@@ -17,7 +24,8 @@ pub struct Camera {
     half_width: f64,
     half_height: f64,
     pixel_size: f64,
-    maximum_recursive_depth: u8
+    maximum_recursive_depth: u8,
+    spectral_samples: usize
 }
 
 impl Camera {
@@ -45,7 +53,8 @@ impl Camera {
             half_width,
             half_height,
             pixel_size,
-            maximum_recursive_depth: 4
+            maximum_recursive_depth: 4,
+            spectral_samples: 0
         }
     }
 
@@ -54,6 +63,11 @@ impl Camera {
         self
     }
 
+    pub fn with_spectral_samples(mut self, spectral_samples: usize) -> Self {
+        self.spectral_samples = spectral_samples;
+        self
+    }
+
     pub fn ray_from_pixel(self, x: usize, y: usize) -> Ray {
         let offset_x = (0.5 + x as f64) * self.pixel_size;
         let offset_y = (0.5 + y as f64) * self.pixel_size;
@@ -77,13 +91,88 @@ impl Camera {
             .par_bridge()
             .for_each(|(x, y)| {
                 let ray = self.ray_from_pixel(x, y);
-                let color = world.clone().color_at(ray, self.maximum_recursive_depth);
-                let mut canvas = canvas_mutex.lock().unwrap();
-                canvas.set_pixel_color(x, y, color);
+                if self.spectral_samples > 0 {
+                    let spectrum = self.trace_spectrum(&world, ray);
+                    let mut canvas = canvas_mutex.lock().unwrap();
+                    canvas.set_pixel_spectrum(x, y, &spectrum);
+                } else {
+                    let color = world.color_at(ray, self.maximum_recursive_depth);
+                    let mut canvas = canvas_mutex.lock().unwrap();
+                    canvas.set_pixel_color(x, y, color);
+                }
             });
         let canvas = canvas_mutex.into_inner().unwrap();
         canvas
     }
+
+    // depth, normal and object of the first visible hit per pixel, for post-processing
+    pub fn render_geometry(self, world: &World) -> GeometryBuffer {
+        let mut buffer = GeometryBuffer::new(self.horizontal_size, self.vertical_size);
+        for (x, y) in (0..self.horizontal_size).cartesian_product(0..self.vertical_size) {
+            let ray = self.ray_from_pixel(x, y);
+            let surface = world.intersect_visible(ray, RayKind::Camera).hit().map(|hit| {
+                let normal = hit.object.normal_at(ray.position(hit.t));
+                Surface {
+                    depth: hit.t,
+                    normal: if normal.dot(ray.direction) > 0. { -normal } else { normal },
+                    object_id: hit.object_id.unwrap_or(usize::MAX)
+                }
+            });
+            buffer.set(x, y, surface);
+        }
+        buffer
+    }
+
+    // grayscale, white where nothing occludes and where the camera ray escapes
+    pub fn render_ambient_occlusion(self, world: &World, ambient_occlusion: AmbientOcclusion) -> Canvas {
+        let canvas_mutex = Mutex::new(Canvas::new(self.horizontal_size, self.vertical_size));
+        (0..self.horizontal_size)
+            .cartesian_product(0..self.vertical_size)
+            .par_bridge()
+            .for_each(|(x, y)| {
+                let ray = self.ray_from_pixel(x, y);
+                let xs = world.intersect_visible(ray, RayKind::Camera);
+                let occlusion = match xs.hit() {
+                    Some(hit) => {
+                        let comps = hit.prepare_computations(ray, &xs);
+                        world.occlusion_at(comps.over_point, comps.normal_v, &ambient_occlusion)
+                    }
+                    None => 1.
+                };
+                let mut canvas = canvas_mutex.lock().unwrap();
+                canvas.set_pixel_color(x, y, Color::new(occlusion, occlusion, occlusion));
+            });
+        canvas_mutex.into_inner().unwrap()
+    }
+
+    pub fn render_bidirectional(self, world: &World, integrator: Bidirectional) -> Canvas {
+        let canvas_mutex = Mutex::new(Canvas::new(self.horizontal_size, self.vertical_size));
+        let sources = light_sources(world);
+        (0..self.horizontal_size)
+            .cartesian_product(0..self.vertical_size)
+            .par_bridge()
+            .for_each(|(x, y)| {
+                let ray = self.ray_from_pixel(x, y);
+                let mut total = Color::black();
+                for sample in 0..integrator.samples {
+                    let mut rng = Rng::from_point(Tuple::point(x as f64, y as f64, sample as f64), 29);
+                    total = total + integrator.radiance_from(world, &sources, ray, &mut rng);
+                }
+                let mut canvas = canvas_mutex.lock().unwrap();
+                canvas.set_pixel_color(x, y, total * (1. / integrator.samples as f64));
+            });
+        canvas_mutex.into_inner().unwrap()
+    }
+
+    fn trace_spectrum(&self, world: &World, ray: Ray) -> Spectrum {
+        let mut spectrum = Spectrum::new();
+        for i in 0..self.spectral_samples {
+            let wavelength = stratified_wavelength(i, self.spectral_samples);
+            let spectral_ray = Ray::spectral(ray.origin, ray.direction, wavelength);
+            spectrum.add_sample(wavelength, world.color_at(spectral_ray, self.maximum_recursive_depth));
+        }
+        spectrum
+    }
 }
 
 impl Equivalence<Camera> for Camera {
@@ -99,13 +188,16 @@ impl Equivalence<Camera> for Camera {
 mod tests_camera {
     use std::f64::consts::PI;
     use crate::assert_equivalent;
-    use crate::color::Color;
     use crate::lights::Light;
     use crate::materials::Material;
-    use crate::object::{Intersectable, Object};
+    use crate::medium::Medium;
+    use crate::object::{Object, Visibility};
+    use crate::outline::Outline;
+    use crate::plane::Plane;
     use crate::sphere::Sphere;
     use crate::transformations::Transform;
     use crate::tuple::Tuple;
+    use crate::volume::{DensityGrid, Volume};
     use crate::world::World;
     use super::*;
 
@@ -181,8 +273,8 @@ mod tests_camera {
         let mut sphere_2 = Sphere::default();
         sphere_2.set_transform(Matrix::scaling(Tuple::vector(0.5, 0.5, 0.5)));
 
-        let s1 = Object::from(sphere);
-        let s2 = Object::from(sphere_2);
+        let s1 = Object::from(sphere.clone());
+        let s2 = Object::from(sphere_2.clone());
 
         World::new(vec![s1, s2], vec![light])
     }
@@ -202,4 +294,74 @@ mod tests_camera {
 
         assert_equivalent!(canvas.get_pixel_color(5, 5), Color::new(0.38066, 0.47583, 0.2855));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn a_spectral_render_without_dispersion_matches_the_rgb_render() {
+        let from = Tuple::point(0., 0., -5.);
+        let to  = Tuple::point(0., 0., 0.);
+        let up = Tuple::vector(0., 1., 0.);
+
+        let camera = Camera::new(11, 11, PI/2.)
+            .with_transform(from.view_transform(to, up))
+            .with_spectral_samples(8);
+
+        let canvas = camera.render(create_default_world());
+
+        assert_equivalent!(canvas.get_pixel_color(5, 5), Color::new(0.38066, 0.47583, 0.2855));
+    }
+
+    #[test]
+    fn outlining_a_render_from_its_geometry() {
+        let mut world = create_default_world();
+        world.objects.truncate(1);
+        let camera = Camera::new(21, 21, PI/6.).with_transform(
+            Tuple::point(0., 0., -5.).view_transform(Tuple::point(0., 0., 0.), Tuple::vector(0., 1., 0.))
+        );
+
+        let buffer = camera.render_geometry(&world);
+        assert_equivalent!(buffer.get(10, 10).unwrap().depth, 4.);
+        assert_equivalent!(buffer.get(10, 10).unwrap().normal, Tuple::vector(0., 0., -1.));
+        assert_eq!(buffer.get(0, 0), None);
+
+        let mut canvas = camera.render(world);
+        let centre = canvas.get_pixel_color(10, 10);
+        Outline::new(Color::new(1., 0., 0.), 1).apply(&mut canvas, &buffer);
+
+        assert_equivalent!(canvas.get_pixel_color(10, 10), centre);
+        assert!((0..21).any(|x| canvas.get_pixel_color(x, 10) == Color::new(1., 0., 0.)));
+    }
+
+    #[test]
+    fn volumes_stay_out_of_the_geometry_buffer() {
+        let mut world = create_default_world();
+        world.objects.truncate(1);
+        let mut smoke = Volume::new(DensityGrid::from_text(b"1 1 1 1").unwrap(), Medium::fog(1.));
+        smoke.set_transform(Matrix::translation(Tuple::vector(0., 0., -2.5)) * Matrix::scaling(Tuple::vector(0.5, 0.5, 0.5)));
+        world.volumes.push(smoke);
+        let camera = Camera::new(21, 21, PI/6.).with_transform(
+            Tuple::point(0., 0., -5.).view_transform(Tuple::point(0., 0., 0.), Tuple::vector(0., 1., 0.))
+        );
+
+        let buffer = camera.render_geometry(&world);
+
+        assert_equivalent!(buffer.get(10, 10).unwrap().depth, 4.);
+        assert_eq!(buffer.get(10, 10).unwrap().object_id, 0);
+    }
+
+    #[test]
+    fn rendering_an_ambient_occlusion_pass() {
+        let mut ceiling = Plane::default();
+        ceiling.set_transform(Matrix::translation(Tuple::vector(0., 1., 0.)));
+        ceiling.set_visibility(Visibility { camera: false, ..Visibility::default() });
+        let world = World::new(vec![Object::from(Plane::default()), Object::from(ceiling)], vec![]);
+        let camera = Camera::new(5, 5, PI/2.).with_transform(
+            Tuple::point(0.5, 0.5, 0.).view_transform(Tuple::point(0., 0., 0.), Tuple::vector(0., 1., 0.))
+        );
+
+        let enclosed = camera.render_ambient_occlusion(&world, AmbientOcclusion::new(16, 100.));
+        let open = camera.render_ambient_occlusion(&world, AmbientOcclusion::new(16, 0.5));
+
+        assert_equivalent!(enclosed.get_pixel_color(2, 2), Color::black());
+        assert_equivalent!(open.get_pixel_color(2, 2), Color::white());
+    }
+}
@@ -1,6 +1,8 @@
 use crate::color::Color;
+use crate::spectrum::Spectrum;
 use std::vec::Vec;
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct Canvas {
     pub width: usize,
     pub height: usize,
@@ -16,6 +18,85 @@ impl Canvas {
         }
     }
 
+    pub fn from_ppm(data: &[u8]) -> Result<Self, String> {
+        let text = String::from_utf8_lossy(data);
+        let mut tokens = text
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or(""))
+            .flat_map(|line| line.split_whitespace());
+
+        if tokens.next() != Some("P3") {
+            return Err(String::from("Invalid PPM. Only P3 is supported"));
+        }
+
+        let mut next_number = || -> Result<f64, String> {
+            let token = tokens.next().ok_or("Invalid PPM. Unexpected end of data")?;
+            token.parse::<f64>().map_err(|_| format!("Invalid PPM. {} is not a number", token))
+        };
+
+        let width = next_number()? as usize;
+        let height = next_number()? as usize;
+        let scale = next_number()?;
+
+        let mut canvas = Canvas::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let red = next_number()? / scale;
+                let green = next_number()? / scale;
+                let blue = next_number()? / scale;
+                canvas.set_pixel_color(x, y, Color::new(red, green, blue));
+            }
+        }
+        Ok(canvas)
+    }
+
+    pub fn from_png(data: &[u8]) -> Result<Self, String> {
+        Canvas::decode_png(data, false)
+    }
+
+    // the alpha channel as a grayscale canvas, images without alpha are fully opaque
+    pub fn alpha_from_png(data: &[u8]) -> Result<Self, String> {
+        Canvas::decode_png(data, true)
+    }
+
+    fn decode_png(data: &[u8], alpha: bool) -> Result<Self, String> {
+        let mut decoder = png::Decoder::new(data);
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let (info, mut reader) = decoder.read_info().map_err(|e| format!("Invalid PNG. {}", e))?;
+
+        let mut buffer = vec![0; info.buffer_size()];
+        reader.next_frame(&mut buffer).map_err(|e| format!("Invalid PNG. {}", e))?;
+
+        let channels = match info.color_type {
+            png::ColorType::Grayscale => 1,
+            png::ColorType::GrayscaleAlpha => 2,
+            png::ColorType::RGB => 3,
+            png::ColorType::RGBA => 4,
+            png::ColorType::Indexed => return Err(String::from("Invalid PNG. Indexed colors were not expanded")),
+        };
+
+        let width = info.width as usize;
+        let height = info.height as usize;
+        let mut canvas = Canvas::new(width, height);
+        for y in 0..height {
+            let row = &buffer[y * info.line_size..];
+            for x in 0..width {
+                let texel = &row[x * channels..];
+                let color = if alpha {
+                    let opacity = if channels % 2 == 0 { texel[channels - 1] as f64 / 255. } else { 1. };
+                    Color::new(opacity, opacity, opacity)
+                } else if channels < 3 {
+                    let gray = texel[0] as f64 / 255.;
+                    Color::new(gray, gray, gray)
+                } else {
+                    Color::new(texel[0] as f64 / 255., texel[1] as f64 / 255., texel[2] as f64 / 255.)
+                };
+                canvas.set_pixel_color(x, y, color);
+            }
+        }
+        Ok(canvas)
+    }
+
     pub fn get_pixel_color(&self, x: usize, y: usize) -> Color {
         self.pixels[self.get_pixel_index(x, y)]
     }
@@ -25,6 +106,10 @@ impl Canvas {
         self.pixels[index] = color;
     }
 
+    pub fn set_pixel_spectrum(&mut self, x: usize, y: usize, spectrum: &Spectrum) {
+        self.set_pixel_color(x, y, spectrum.to_rgb());
+    }
+
     fn get_pixel_index(&self, x: usize, y: usize) -> usize {
         y * self.width + x
     }
@@ -231,5 +316,101 @@ mod tests_canvas {
 
         assert_eq!(expected_result, expected_result);
     }
-}
 
+    #[test]
+    fn reading_a_ppm_returns_a_canvas_of_the_right_size() {
+        let ppm = String::from("P3\n10 2\n255\n").into_bytes();
+        let mut data = ppm;
+        data.extend(String::from("0 0 0 ").repeat(20).into_bytes());
+
+        let canvas = Canvas::from_ppm(&data).unwrap();
+
+        assert_eq!(canvas.width, 10);
+        assert_eq!(canvas.height, 2);
+    }
+
+    #[test]
+    fn reading_pixel_data_from_a_ppm_file() {
+        let ppm = String::from("\
+        P3\n\
+        4 3\n\
+        255\n\
+        255 127 0  0 127 255  127 255 0  255 255 255\n\
+        0 0 0  255 0 0  0 255 0  0 0 255\n\
+        255 255 0  0 255 255  255 0 255  127 127 127\n"
+        ).into_bytes();
+
+        let canvas = Canvas::from_ppm(&ppm).unwrap();
+
+        assert_equivalent!(canvas.get_pixel_color(0, 0), Color::new(1., 0.49804, 0.));
+        assert_equivalent!(canvas.get_pixel_color(1, 0), Color::new(0., 0.49804, 1.));
+        assert_equivalent!(canvas.get_pixel_color(3, 0), Color::new(1., 1., 1.));
+        assert_equivalent!(canvas.get_pixel_color(1, 1), Color::new(1., 0., 0.));
+        assert_equivalent!(canvas.get_pixel_color(3, 2), Color::new(0.49804, 0.49804, 0.49804));
+    }
+
+    #[test]
+    fn ppm_parsing_ignores_comment_lines_and_respects_the_scale() {
+        let ppm = String::from("\
+        P3\n\
+        # this is a comment\n\
+        1 1\n\
+        100\n\
+        100 50 0\n"
+        ).into_bytes();
+
+        let canvas = Canvas::from_ppm(&ppm).unwrap();
+
+        assert_equivalent!(canvas.get_pixel_color(0, 0), Color::new(1., 0.5, 0.));
+    }
+
+    #[test]
+    fn reading_a_ppm_with_the_wrong_magic_number_fails() {
+        let ppm = String::from("P32\n1 1\n255\n0 0 0\n").into_bytes();
+
+        assert!(Canvas::from_ppm(&ppm).is_err());
+    }
+
+    #[test]
+    fn a_canvas_survives_a_png_round_trip() {
+        let mut canvas = Canvas::new(3, 2);
+        canvas.set_pixel_color(0, 0, Color::new(1., 0., 0.));
+        canvas.set_pixel_color(2, 1, Color::new(0., 0., 1.));
+        canvas.set_pixel_color(1, 1, Color::new(0.2, 0.4, 0.6));
+
+        let result = Canvas::from_png(&canvas.to_png()).unwrap();
+
+        assert_eq!(result.width, 3);
+        assert_eq!(result.height, 2);
+        assert_equivalent!(result.get_pixel_color(0, 0), Color::new(1., 0., 0.));
+        assert_equivalent!(result.get_pixel_color(2, 1), Color::new(0., 0., 1.));
+        assert_equivalent!(result.get_pixel_color(1, 1), Color::new(0.2, 0.4, 0.6));
+    }
+
+    #[test]
+    fn reading_the_alpha_channel_of_a_png() {
+        let mut data = Vec::new();
+        let mut encoder = png::Encoder::new(&mut data, 2, 1);
+        encoder.set_color(png::ColorType::RGBA);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[255, 0, 0, 255, 0, 255, 0, 51]).unwrap();
+        drop(writer);
+
+        let alpha = Canvas::alpha_from_png(&data).unwrap();
+        let color = Canvas::from_png(&data).unwrap();
+
+        assert_equivalent!(alpha.get_pixel_color(0, 0), Color::white());
+        assert_equivalent!(alpha.get_pixel_color(1, 0), Color::new(0.2, 0.2, 0.2));
+        assert_equivalent!(color.get_pixel_color(1, 0), Color::new(0., 1., 0.));
+    }
+
+    #[test]
+    fn an_opaque_canvas_has_a_white_alpha_channel() {
+        let canvas = Canvas::new(2, 2);
+
+        let alpha = Canvas::alpha_from_png(&canvas.to_png()).unwrap();
+
+        assert_equivalent!(alpha.get_pixel_color(1, 1), Color::white());
+    }
+}
@@ -0,0 +1,118 @@
+use crate::color::Color;
+
+// complex index of refraction n + ik, one value per rgb channel
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Conductor {
+    pub eta: Color,
+    pub k: Color
+}
+
+fn fresnel(cos: f64, eta: f64, k: f64) -> f64 {
+    let cos2 = cos * cos;
+    let sin2 = 1. - cos2;
+    let t0 = eta * eta - k * k - sin2;
+    let a2_plus_b2 = (t0 * t0 + 4. * eta * eta * k * k).sqrt();
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0.).sqrt();
+
+    let t1 = a2_plus_b2 + cos2;
+    let t2 = 2. * cos * a;
+    let rs = (t1 - t2) / (t1 + t2);
+
+    let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+
+    (rs + rp) / 2.
+}
+
+impl Conductor {
+    pub fn new(eta: Color, k: Color) -> Self {
+        Conductor { eta, k }
+    }
+
+    pub fn gold() -> Self {
+        Conductor::new(Color::new(0.143, 0.374, 1.442), Color::new(3.983, 2.385, 1.603))
+    }
+
+    pub fn silver() -> Self {
+        Conductor::new(Color::new(0.155, 0.117, 0.138), Color::new(4.828, 3.122, 2.147))
+    }
+
+    pub fn copper() -> Self {
+        Conductor::new(Color::new(0.200, 0.924, 1.102), Color::new(3.912, 2.452, 2.142))
+    }
+
+    pub fn aluminium() -> Self {
+        Conductor::new(Color::new(1.657, 0.880, 0.521), Color::new(9.224, 6.270, 4.837))
+    }
+
+    pub fn iron() -> Self {
+        Conductor::new(Color::new(2.911, 2.950, 2.584), Color::new(3.089, 2.932, 2.767))
+    }
+
+    pub fn chromium() -> Self {
+        Conductor::new(Color::new(3.180, 3.180, 2.010), Color::new(3.300, 3.330, 3.040))
+    }
+
+    pub fn lookup(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "gold" | "au" => Some(Conductor::gold()),
+            "silver" | "ag" => Some(Conductor::silver()),
+            "copper" | "cu" => Some(Conductor::copper()),
+            "aluminium" | "aluminum" | "al" => Some(Conductor::aluminium()),
+            "iron" | "fe" => Some(Conductor::iron()),
+            "chromium" | "cr" => Some(Conductor::chromium()),
+            _ => None
+        }
+    }
+
+    // cos is the cosine between the eye and the surface normal, the outside medium is taken as air
+    pub fn reflectance(&self, cos: f64) -> Color {
+        let cos = cos.clamp(0., 1.);
+        Color::new(
+            fresnel(cos, self.eta.red, self.k.red),
+            fresnel(cos, self.eta.green, self.k.green),
+            fresnel(cos, self.eta.blue, self.k.blue)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests_conductor {
+    use crate::assert_equivalent;
+    use crate::equivalent::Equivalence;
+    use super::*;
+
+    #[test]
+    fn reflectance_at_normal_incidence() {
+        let gold = Conductor::gold();
+        let normal_incidence = |eta: f64, k: f64| ((eta - 1.).powi(2) + k * k) / ((eta + 1.).powi(2) + k * k);
+
+        assert_equivalent!(gold.reflectance(1.), Color::new(
+            normal_incidence(0.143, 3.983),
+            normal_incidence(0.374, 2.385),
+            normal_incidence(1.442, 1.603)
+        ));
+    }
+
+    #[test]
+    fn gold_reflects_more_red_than_blue() {
+        let color = Conductor::gold().reflectance(1.);
+
+        assert!(color.red > 0.9);
+        assert!(color.blue < 0.4);
+    }
+
+    #[test]
+    fn every_metal_is_a_perfect_mirror_at_grazing_angles() {
+        for metal in ["gold", "silver", "copper", "aluminium", "iron", "chromium"] {
+            assert_equivalent!(Conductor::lookup(metal).unwrap().reflectance(0.), Color::white());
+        }
+    }
+
+    #[test]
+    fn looking_up_an_unknown_metal() {
+        assert_eq!(Conductor::lookup("Cu"), Some(Conductor::copper()));
+        assert_eq!(Conductor::lookup("unobtainium"), None);
+    }
+}
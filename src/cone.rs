@@ -3,14 +3,15 @@ use crate::EPSILON;
 use crate::intersection::{Intersection, Intersections};
 use crate::materials::Material;
 use crate::matrix::Matrix;
-use crate::object::{Intersectable, Object};
+use crate::object::{Intersectable, Object, Visibility};
 use crate::ray::Ray;
 use crate::tuple::Tuple;
 use crate::equivalent::Equivalence;
+use crate::uv::cylindrical_map;
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Cone {
-    pub origin: Tuple, pub material: Material, pub transform: Matrix<4>, pub minimum: f64, pub maximum: f64, pub closed: bool
+    pub origin: Tuple, pub material: Material, pub transform: Matrix<4>, pub visibility: Visibility, pub name: Option<String>, pub minimum: f64, pub maximum: f64, pub closed: bool
 }
 
 impl Cone {
@@ -19,10 +20,10 @@ impl Cone {
         let material = Material::phong();
         let minimum =  -f64::INFINITY;
         let maximum = f64::INFINITY;
-        Cone { origin, material, transform, minimum, maximum, closed: false }
+        Cone { origin, material, transform, visibility: Visibility::default(), name: None, minimum, maximum, closed: false }
     }
 
-    fn check_cap(self, ray: Ray, t: f64) -> bool {
+    fn check_cap(&self, ray: Ray, t: f64) -> bool {
         let x = ray.origin.x + t * ray.direction.x;
         let y = ray.origin.y + t * ray.direction.y;
         let z = ray.origin.z + t * ray.direction.z;
@@ -30,6 +31,11 @@ impl Cone {
         (x.powi(2) + z.powi(2)) <= y.abs()
     }
 
+    fn is_on_cap(&self, local_point: Tuple) -> bool {
+        let dist = local_point.x.powi(2) + local_point.z.powi(2);
+        dist < 1. && (local_point.y >= self.maximum - EPSILON || local_point.y <= self.minimum + EPSILON)
+    }
+
     fn intersect_caps(&self, ray: Ray, xs: &mut Intersections) {
         if !self.closed || ray.direction.y.equivalent(0.) {
             return;
@@ -37,12 +43,12 @@ impl Cone {
 
         let mut t = (self.minimum - ray.origin.y) / ray.direction.y;
         if self.check_cap(ray, t) {
-            xs.data.push(Intersection::new(t, Object::from(*self)));
+            xs.data.push(Intersection::new(t, Object::from(self.clone())));
         }
 
         t = (self.maximum - ray.origin.y) / ray.direction.y;
         if self.check_cap(ray, t) {
-            xs.data.push(Intersection::new(t, Object::from(*self)));
+            xs.data.push(Intersection::new(t, Object::from(self.clone())));
         }
     }
 }
@@ -71,7 +77,7 @@ impl Intersectable for Cone {
                 return Intersections::new(vec![]);
             }
             let t = -c / (2. * b);
-            xs.push(Intersection::new(t, Object::from(*self)));
+            xs.push(Intersection::new(t, Object::from(self.clone())));
         }
 
         let disc = b.powi(2) - 4. * a * c;
@@ -89,12 +95,12 @@ impl Intersectable for Cone {
 
         let y0 = local_ray.origin.y + t0 * local_ray.direction.y;
         if self.minimum < y0 && y0 < self.maximum {
-            xs.push(Intersection::new(t0, Object::from(*self)));
+            xs.push(Intersection::new(t0, Object::from(self.clone())));
         }
 
         let y1 = local_ray.origin.y + t1 * local_ray.direction.y;
         if self.minimum < y1 && y1 < self.maximum {
-            xs.push(Intersection::new(t1, Object::from(*self)));
+            xs.push(Intersection::new(t1, Object::from(self.clone())));
         }
 
         let mut intersections = Intersections::new(xs);
@@ -119,8 +125,26 @@ impl Intersectable for Cone {
         Tuple::vector(local_point.x, y, local_point.z)
     }
 
+    fn local_uv_at(&self, local_point: Tuple) -> (f64, f64) {
+        if self.is_on_cap(local_point) {
+            return ((local_point.x + 1.) / 2., (local_point.z + 1.) / 2.);
+        }
+        cylindrical_map(local_point)
+    }
+
+    fn local_tangent_at(&self, local_point: Tuple) -> Tuple {
+        if self.is_on_cap(local_point) {
+            return Tuple::vector(1., 0., 0.);
+        }
+        Tuple::vector(-local_point.z, 0., local_point.x)
+    }
+
     fn material(&self) -> Material {
-        self.material
+        self.material.clone()
+    }
+
+    fn material_ref(&self) -> &Material {
+        &self.material
     }
 
     fn transform(&self) -> Matrix<4> {
@@ -134,6 +158,22 @@ impl Intersectable for Cone {
     fn set_transform(&mut self, transform: Matrix<4>) {
         self.transform = transform
     }
+
+    fn visibility(&self) -> Visibility {
+        self.visibility
+    }
+
+    fn set_visibility(&mut self, visibility: Visibility) {
+        self.visibility = visibility
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    fn set_name(&mut self, name: &str) {
+        self.name = Some(name.to_string())
+    }
 }
 
 #[cfg(test)]
@@ -217,4 +257,18 @@ mod tests_cone {
         assert_equivalent!(cone.local_normal_at(Tuple::point(1., 1., 1.)), Tuple::vector(1., -f64::from(2.).sqrt(), 1.));
         assert_equivalent!(cone.local_normal_at(Tuple::point(-1., -1., 0.)), Tuple::vector(-1., 1., 0.));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn surface_coordinates_on_the_side_and_caps_of_a_cone() {
+        let cone = Cone { minimum: -1., maximum: 0., closed: true, ..Cone::default() };
+
+        let (u, v) = cone.local_uv_at(Tuple::point(0., -0.5, -0.5));
+        assert_equivalent!(u, 0.);
+        assert_equivalent!(v, 0.5);
+        assert_equivalent!(cone.local_tangent_at(Tuple::point(0., -0.5, -0.5)), Tuple::vector(0.5, 0., 0.));
+
+        let (u, v) = cone.local_uv_at(Tuple::point(0.5, -1., 0.));
+        assert_equivalent!(u, 0.75);
+        assert_equivalent!(v, 0.5);
+    }
+}
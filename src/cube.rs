@@ -3,23 +3,24 @@ use crate::EPSILON;
 use crate::intersection::{Intersection, Intersections};
 use crate::materials::Material;
 use crate::matrix::Matrix;
-use crate::object::{Intersectable, Object};
+use crate::object::{Intersectable, Object, Visibility};
 use crate::ray::Ray;
 use crate::tuple::Tuple;
+use crate::uv::{cube_map, face_from_point, CubeFace};
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Cube {
-    pub origin: Tuple, pub material: Material, pub transform: Matrix<4>, pub radius: Tuple
+    pub origin: Tuple, pub material: Material, pub transform: Matrix<4>, pub visibility: Visibility, pub name: Option<String>, pub radius: Tuple
 }
 
 impl Cube {
     pub fn new(origin: Tuple, radius: Tuple) -> Self {
         let transform = Matrix::identity();
         let material = Material::phong();
-        Cube { origin, material, transform, radius }
+        Cube { origin, material, transform, visibility: Visibility::default(), name: None, radius }
     }
 
-    fn check_axis(self, origin: f64, direction: f64, radius: f64) -> (f64, f64) {
+    fn check_axis(&self, origin: f64, direction: f64, radius: f64) -> (f64, f64) {
         let tmin_numerator = -radius - origin;
         let tmax_numerator = radius - origin;
 
@@ -64,8 +65,8 @@ impl Intersectable for Cube {
         }
 
         Intersections::new(vec![
-            Intersection::new(tmin, Object::from(*self)),
-            Intersection::new(tmax, Object::from(*self))
+            Intersection::new(tmin, Object::from(self.clone())),
+            Intersection::new(tmax, Object::from(self.clone()))
         ])
     }
 
@@ -82,8 +83,25 @@ impl Intersectable for Cube {
         return Tuple::vector(0., 0., local_point.z);
     }
 
+    fn local_uv_at(&self, local_point: Tuple) -> (f64, f64) {
+        cube_map(local_point)
+    }
+
+    fn local_tangent_at(&self, local_point: Tuple) -> Tuple {
+        match face_from_point(local_point) {
+            CubeFace::Front | CubeFace::Up | CubeFace::Down => Tuple::vector(1., 0., 0.),
+            CubeFace::Back => Tuple::vector(-1., 0., 0.),
+            CubeFace::Left => Tuple::vector(0., 0., 1.),
+            CubeFace::Right => Tuple::vector(0., 0., -1.),
+        }
+    }
+
     fn material(&self) -> Material {
-        self.material
+        self.material.clone()
+    }
+
+    fn material_ref(&self) -> &Material {
+        &self.material
     }
 
     fn transform(&self) -> Matrix<4> {
@@ -97,6 +115,22 @@ impl Intersectable for Cube {
     fn set_transform(&mut self, transform: Matrix<4>) {
         self.transform = transform
     }
+
+    fn visibility(&self) -> Visibility {
+        self.visibility
+    }
+
+    fn set_visibility(&mut self, visibility: Visibility) {
+        self.visibility = visibility
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    fn set_name(&mut self, name: &str) {
+        self.name = Some(name.to_string())
+    }
 }
 
 #[cfg(test)]
@@ -175,4 +209,16 @@ mod tests_cube {
         assert_equivalent!(c.local_normal_at(pont7), Tuple::vector(1., 0., 0.));
         assert_equivalent!(c.local_normal_at(pont8), Tuple::vector(-1., 0., 0.));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn the_tangent_on_each_face_of_a_cube() {
+        let c = Cube::default();
+
+        assert_equivalent!(c.local_tangent_at(Tuple::point(0.2, 0.3, 1.)), Tuple::vector(1., 0., 0.));
+        assert_equivalent!(c.local_tangent_at(Tuple::point(0.2, 0.3, -1.)), Tuple::vector(-1., 0., 0.));
+        assert_equivalent!(c.local_tangent_at(Tuple::point(-1., 0.3, 0.2)), Tuple::vector(0., 0., 1.));
+        assert_equivalent!(c.local_tangent_at(Tuple::point(1., 0.3, 0.2)), Tuple::vector(0., 0., -1.));
+        assert_equivalent!(c.local_tangent_at(Tuple::point(0.2, 1., 0.3)), Tuple::vector(1., 0., 0.));
+        assert_equivalent!(c.local_tangent_at(Tuple::point(0.2, -1., 0.3)), Tuple::vector(1., 0., 0.));
+    }
+}
@@ -0,0 +1,89 @@
+use crate::object::{Intersectable, Object};
+use crate::patterns::{Incuse, Patterns};
+use crate::tuple::Tuple;
+use crate::uv::{UvImage, UvPattern};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum CutoutMask {
+    Pattern(Box<Patterns>),
+    Image(UvImage)
+}
+
+impl From<Patterns> for CutoutMask {
+    fn from(pattern: Patterns) -> Self {
+        CutoutMask::Pattern(Box::new(pattern))
+    }
+}
+
+impl From<UvImage> for CutoutMask {
+    fn from(image: UvImage) -> Self {
+        CutoutMask::Image(image)
+    }
+}
+
+// hits where the mask's grayscale is below the threshold are skipped, as if the surface had a hole there
+#[derive(Debug, PartialEq, Clone)]
+pub struct Cutout {
+    pub mask: CutoutMask,
+    pub threshold: f64
+}
+
+impl Cutout {
+    pub fn new(mask: CutoutMask) -> Self {
+        Cutout { mask, threshold: 0.5 }
+    }
+
+    pub fn with_threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    pub fn opacity_at(&self, object: &Object, point: Tuple) -> f64 {
+        let color = match self.mask {
+            CutoutMask::Pattern(ref pattern) => pattern.color_at_object(object, point),
+            CutoutMask::Image(ref image) => {
+                let (u, v) = object.uv_at(point);
+                image.uv_color_at(u, v)
+            }
+        };
+        (color.red + color.green + color.blue) / 3.
+    }
+
+    pub fn is_opaque_at(&self, object: &Object, point: Tuple) -> bool {
+        self.opacity_at(object, point) >= self.threshold
+    }
+}
+
+#[cfg(test)]
+mod tests_cutout {
+    use crate::assert_equivalent;
+    use crate::canvas::Canvas;
+    use crate::color::Color;
+    use crate::equivalent::Equivalence;
+    use crate::patterns::Stripe;
+    use crate::plane::Plane;
+    use crate::uv::Filter;
+    use super::*;
+
+    #[test]
+    fn a_pattern_mask_cuts_out_its_dark_stripes() {
+        let cutout = Cutout::new(CutoutMask::from(Patterns::from(Stripe::new(Color::white(), Color::black()))));
+        let object = Object::from(Plane::default());
+
+        assert!(cutout.is_opaque_at(&object, Tuple::point(0.5, 0., 0.)));
+        assert!(!cutout.is_opaque_at(&object, Tuple::point(1.5, 0., 0.)));
+    }
+
+    #[test]
+    fn an_image_mask_reads_alpha_through_the_uv_mapping() {
+        let mut alpha = Canvas::new(2, 1);
+        alpha.set_pixel_color(0, 0, Color::new(0.2, 0.2, 0.2));
+        alpha.set_pixel_color(1, 0, Color::white());
+        let cutout = Cutout::new(CutoutMask::from(UvImage::new(alpha).with_filter(Filter::Nearest)));
+        let object = Object::from(Plane::default());
+
+        assert_equivalent!(cutout.opacity_at(&object, Tuple::point(0.25, 0., 0.5)), 0.2);
+        assert!(cutout.is_opaque_at(&object, Tuple::point(0.75, 0., 0.5)));
+        assert!(cutout.clone().with_threshold(0.1).is_opaque_at(&object, Tuple::point(0.25, 0., 0.5)));
+    }
+}
@@ -1,12 +1,18 @@
+use std::borrow::Cow;
 use crate::EPSILON;
+use crate::color::Color;
+use crate::materials::Material;
 use crate::object::{Intersectable, Object};
 use crate::ray::Ray;
 use crate::tuple::Tuple;
 
-#[derive(PartialEq, Copy, Clone, Debug)]
-pub struct Computations {
+#[derive(PartialEq, Clone, Debug)]
+pub struct Computations<'a> {
     pub t: f64,
     pub object: Object,
+    pub object_id: Option<usize>,
+    // borrowed from the hit object unless a mix had to blend a new material
+    pub material: Cow<'a, Material>,
     pub point: Tuple,
     pub over_point: Tuple,
     pub under_point: Tuple,
@@ -15,24 +21,40 @@ pub struct Computations {
     pub reflect_v: Tuple,
     pub n1: f64,
     pub n2: f64,
-    pub inside: bool
+    pub inside: bool,
+    pub u: f64,
+    pub v: f64,
+    pub tangent_v: Tuple,
+    pub bitangent_v: Tuple,
+    pub wavelength: Option<f64>,
+    pub scattered: bool
 }
 
-impl Computations {
-    fn from(intersection: Intersection, ray: Ray) -> Self {
+impl<'a> Computations<'a> {
+    fn from(intersection: &'a Intersection, ray: Ray) -> Self {
         let t = intersection.t;
-        let object = intersection.object;
+        let object = intersection.object.clone();
         let point = ray.position(intersection.t);
         let eye_v = -ray.direction;
-        let mut normal_v = intersection.object.normal_at(point);
+        // one inversion serves the normal, the surface coordinates and the tangent
+        let inverse = object.transform().inverse();
+        let local_point = inverse * point;
+        let mut normal_v = object.normal_from_local(&inverse, local_point);
         let mut inside = false;
         if normal_v.dot(eye_v) < 0. {
             inside = true;
             normal_v = -normal_v;
         }
-        Computations {
+        let (u, v) = object.local_uv_at(local_point);
+        let tangent_v = object.tangent_from_local(local_point, normal_v);
+        // the book's coordinates are left handed, so this points along increasing v
+        let bitangent_v = tangent_v.cross(normal_v);
+        let material = intersection.object.material_ref().at(&intersection.object, point);
+        let mut comps = Computations {
             t,
             object,
+            object_id: intersection.object_id,
+            material,
             point,
             over_point: point + normal_v * EPSILON,
             under_point: point - normal_v * EPSILON,
@@ -41,11 +63,41 @@ impl Computations {
             reflect_v: ray.direction.reflect(normal_v),
             n1: 0.,
             n2: 0.,
-            inside
+            inside,
+            u,
+            v,
+            tangent_v,
+            bitangent_v,
+            wavelength: ray.wavelength,
+            scattered: ray.scattered
+        };
+        // over and under points keep the geometric normal so perturbed normals don't cause acne
+        if let Some(normal_map) = &comps.material.normal_map {
+            comps.normal_v = normal_map.perturb(&comps, comps.material.normal_strength);
+            comps.reflect_v = ray.direction.reflect(comps.normal_v);
+        }
+        comps
+    }
+
+    pub fn spawn_ray(&self, origin: Tuple, direction: Tuple) -> Ray {
+        let mut ray = Ray::new(origin, direction);
+        ray.wavelength = self.wavelength;
+        ray.scattered = self.scattered;
+        ray
+    }
+
+    // per channel fresnel weight, a thin film on the material makes it iridescent
+    pub fn reflectance(&self) -> Color {
+        match self.material.thin_film {
+            Some(film) => film.reflectance(self.eye_v.dot(self.normal_v), self.n1, self.n2, self.wavelength),
+            None => {
+                let reflectance = self.schlick();
+                Color::new(reflectance, reflectance, reflectance)
+            }
         }
     }
 
-    pub fn schlick(self) -> f64 {
+    pub fn schlick(&self) -> f64 {
         let mut cos = self.eye_v.dot(self.normal_v);
         if self.n1 > self.n2 {
             let n = self.n1 / self.n2;
@@ -62,26 +114,28 @@ impl Computations {
 }
 
 
-#[derive(PartialEq, Copy, Clone, Debug)]
+#[derive(PartialEq, Clone, Debug)]
 pub struct Intersection {
     pub t: f64,
-    pub object: Object
+    pub object: Object,
+    // position of the object in World::objects, stamped when the world intersects it
+    pub object_id: Option<usize>
 }
 
 impl Intersection {
     pub fn new(t: f64, object: Object) -> Self {
-        Intersection { t, object }
+        Intersection { t, object, object_id: None }
     }
 
-    pub fn prepare_computations(self, ray: Ray, xs: &Intersections) -> Computations {
+    pub fn prepare_computations(&self, ray: Ray, xs: &Intersections) -> Computations<'_> {
         let mut comps = Computations::from(self, ray);
         let mut containers: Vec<Object> = vec![];
         for intersect in xs.data.iter() {
-            if intersect == &self {
+            if intersect == self {
                 if containers.is_empty() {
                     comps.n1 = 1.0;
                 } else {
-                    comps.n1 = containers.last().unwrap().material().reflactive_index;
+                    comps.n1 = containers.last().unwrap().material_ref().refractive_index_at(ray.wavelength);
                 }
             }
 
@@ -89,14 +143,14 @@ impl Intersection {
                 let index = containers.iter().position(|x| *x == intersect.object).unwrap();
                 containers.remove(index);
             } else {
-                containers.push(intersect.object);
+                containers.push(intersect.object.clone());
             }
 
-            if intersect == &self {
+            if intersect == self {
                 if containers.is_empty() {
                     comps.n2 = 1.0;
                 } else {
-                    comps.n2 = containers.last().unwrap().material().reflactive_index;
+                    comps.n2 = containers.last().unwrap().material_ref().refractive_index_at(ray.wavelength);
                 }
                 break;
             }
@@ -119,7 +173,7 @@ impl Intersections {
     pub fn hit(&self) -> Option<Intersection> {
         for intersection in self.data.iter() {
             if intersection.t > 0.0 {
-                return Some(*intersection);
+                return Some(intersection.clone());
             }
         }
         None
@@ -140,24 +194,31 @@ mod tests_intersection {
     use crate::{assert_equivalent, EPSILON};
     use crate::equivalent::Equivalence;
     use crate::intersection::{Computations, Intersection, Intersections, Object};
+    use crate::color::Color;
+    use crate::materials::Material;
     use crate::matrix::Matrix;
+    use crate::normal_map::NormalMap;
     use crate::object::Intersectable;
+    use crate::patterns::{LinearGradient, Patterns, Stripe};
     use crate::plane::Plane;
     use crate::ray::Ray;
+    use crate::spectrum::Dispersion;
     use crate::sphere::Sphere;
+    use crate::thin_film::ThinFilm;
+    use crate::triangle::Triangle;
     use crate::tuple::Tuple;
 
     #[test]
     pub fn aggregating_intersection() {
         let sphere = Sphere::new(Tuple::point(0., 0., 0.), 1.);
 
-        let intersect1 = Intersection::new(1.,  Object::from(sphere));
-        let intersect2 = Intersection::new(2.,  Object::from(sphere));
+        let intersect1 = Intersection::new(1.,  Object::from(sphere.clone()));
+        let intersect2 = Intersection::new(2.,  Object::from(sphere.clone()));
 
         let intersections = Intersections::new(vec![intersect1, intersect2]);
 
         assert_eq!(intersections.data.len(), 2);
-        assert_eq!(intersections.data[0].object, Object::from(sphere));
+        assert_eq!(intersections.data[0].object, Object::from(sphere.clone()));
         assert_eq!(intersections.data[1].object, Object::from(sphere));
     }
 
@@ -168,7 +229,7 @@ mod tests_intersection {
 
         let xs = sphere.intersect(ray);
         assert_eq!(xs.data.len(), 2);
-        assert_eq!(xs.data[0].object, Object::from(sphere));
+        assert_eq!(xs.data[0].object, Object::from(sphere.clone()));
         assert_eq!(xs.data[1].object, Object::from(sphere));
     }
 
@@ -176,10 +237,10 @@ mod tests_intersection {
     fn the_hit_when_all_intersection_have_positive_t() {
         let sphere = Sphere::new(Tuple::point(0., 0., 0.), 1.);
 
-        let intersect1 = Intersection::new(1.,  Object::from(sphere));
+        let intersect1 = Intersection::new(1.,  Object::from(sphere.clone()));
         let intersect2 = Intersection::new(2.,  Object::from(sphere));
 
-        let intersections = Intersections::new(vec![intersect2, intersect1]);
+        let intersections = Intersections::new(vec![intersect2, intersect1.clone()]);
 
         assert_eq!(intersections.hit(), Some(intersect1));
     }
@@ -188,10 +249,10 @@ mod tests_intersection {
     fn the_hit_when_some_intersection_have_negative_t() {
         let sphere = Sphere::new(Tuple::point(0., 0., 0.), 1.);
 
-        let intersect1 = Intersection::new(-1.,  Object::from(sphere));
+        let intersect1 = Intersection::new(-1.,  Object::from(sphere.clone()));
         let intersect2 = Intersection::new(1.,  Object::from(sphere));
 
-        let intersections = Intersections::new(vec![intersect2, intersect1]);
+        let intersections = Intersections::new(vec![intersect2.clone(), intersect1]);
 
         assert_eq!(intersections.hit(), Some(intersect2));
     }
@@ -200,7 +261,7 @@ mod tests_intersection {
     fn the_hit_when_all_intersection_have_negative_t() {
         let sphere = Sphere::new(Tuple::point(0., 0., 0.), 1.);
 
-        let intersect1 = Intersection::new(-2.,  Object::from(sphere));
+        let intersect1 = Intersection::new(-2.,  Object::from(sphere.clone()));
         let intersect2 = Intersection::new(-1.,  Object::from(sphere));
 
         let intersections = Intersections::new(vec![intersect2, intersect1]);
@@ -212,12 +273,12 @@ mod tests_intersection {
     fn the_hit_is_always_the_have_lowest_nonnegative_intersection() {
         let sphere = Sphere::new(Tuple::point(0., 0., 0.), 1.);
 
-        let intersect1 = Intersection::new(5.,  Object::from(sphere));
-        let intersect2 = Intersection::new(7.,  Object::from(sphere));
-        let intersect3 = Intersection::new(-3.,  Object::from(sphere));
+        let intersect1 = Intersection::new(5.,  Object::from(sphere.clone()));
+        let intersect2 = Intersection::new(7.,  Object::from(sphere.clone()));
+        let intersect3 = Intersection::new(-3.,  Object::from(sphere.clone()));
         let intersect4 = Intersection::new(2.,  Object::from(sphere));
 
-        let intersections = Intersections::new(vec![intersect1, intersect2, intersect3, intersect4]);
+        let intersections = Intersections::new(vec![intersect1, intersect2, intersect3, intersect4.clone()]);
 
         assert_eq!(intersections.hit(), Some(intersect4));
     }
@@ -228,7 +289,7 @@ mod tests_intersection {
 
         let s = Object::from(Sphere::default());
         let intersect = Intersection::new(4., s);
-        let xs = Intersections::new(vec![intersect]);
+        let xs = Intersections::new(vec![intersect.clone()]);
         let comps = intersect.prepare_computations(ray, &xs);
 
         assert_eq!(comps.t, intersect.t);
@@ -243,7 +304,7 @@ mod tests_intersection {
 
         let s = Object::from(Sphere::default());
         let intersect = Intersection::new(4., s);
-        let xs = Intersections::new(vec![intersect]);
+        let xs = Intersections::new(vec![intersect.clone()]);
         let comps = intersect.prepare_computations(ray, &xs);
 
         assert!(!comps.inside);
@@ -257,7 +318,7 @@ mod tests_intersection {
         shape.transform = Matrix::translation(Tuple::vector(0., 0., 1.));
 
         let intersection = Intersection::new(5., Object::from(shape));
-        let xs = Intersections::new(vec![intersection]);
+        let xs = Intersections::new(vec![intersection.clone()]);
         let comps = intersection.prepare_computations(ray, &xs);
 
         assert!(comps.over_point.z < -EPSILON/2.);
@@ -269,7 +330,7 @@ mod tests_intersection {
         let shape = Plane::default();
         let ray = Ray::new(Tuple::point(0., 1., -1.), Tuple::vector(0., -f64::from(2.).sqrt() / 2., f64::from(2.).sqrt() / 2.));
         let intersection = Intersection::new(f64::from(2.).sqrt(), Object::from(shape));
-        let xs = Intersections::new(vec![intersection]);
+        let xs = Intersections::new(vec![intersection.clone()]);
         let comps = intersection.prepare_computations(ray, &xs);
 
         assert_eq!(comps.reflect_v, Tuple::vector(0., f64::from(2.).sqrt() / 2., f64::from(2.).sqrt() / 2.));
@@ -291,12 +352,12 @@ mod tests_intersection {
 
         let ray = Ray::new(Tuple::point(0., 0., -4.), Tuple::vector(0., 0., 1.));
 
-        let intersect1 = Intersection::new(2.,  Object::from(a));
-        let intersect2 = Intersection::new(2.75,  Object::from(b));
-        let intersect3 = Intersection::new(3.25,  Object::from(c));
-        let intersect4 = Intersection::new(4.75,  Object::from(b));
-        let intersect5 = Intersection::new(5.25,  Object::from(c));
-        let intersect6 = Intersection::new(6.,  Object::from(a));
+        let intersect1 = Intersection::new(2.,  Object::from(a.clone()));
+        let intersect2 = Intersection::new(2.75,  Object::from(b.clone()));
+        let intersect3 = Intersection::new(3.25,  Object::from(c.clone()));
+        let intersect4 = Intersection::new(4.75,  Object::from(b.clone()));
+        let intersect5 = Intersection::new(5.25,  Object::from(c.clone()));
+        let intersect6 = Intersection::new(6.,  Object::from(a.clone()));
 
         let xs = Intersections::new(vec![intersect1, intersect2, intersect3, intersect4, intersect5, intersect6]);
         let comp0 = xs.data[0].prepare_computations(ray, &xs);
@@ -332,7 +393,7 @@ mod tests_intersection {
         let mut shape = Sphere::grass(1.);
         shape.set_transform(Matrix::translation(Tuple::vector(0., 0., 1.0)));
 
-        let intersect = Intersection::new(5.,  Object::from(shape));
+        let intersect = Intersection::new(5.,  Object::from(shape.clone()));
         let xs = Intersections::new(vec![intersect]);
         let comp: Computations = xs.data[0].prepare_computations(ray, &xs);
 
@@ -344,7 +405,7 @@ mod tests_intersection {
     fn the_schlick_approximation_under_total_internal_reflection() {
         let shape = Sphere::grass(1.);
         let ray = Ray::new(Tuple::point(0., 0., f64::from(2.).sqrt() / 2.), Tuple::vector(0., 1., 0.));
-        let intersect1 = Intersection::new(-f64::from(2.).sqrt() / 2.,  Object::from(shape));
+        let intersect1 = Intersection::new(-f64::from(2.).sqrt() / 2.,  Object::from(shape.clone()));
         let intersect2 = Intersection::new(f64::from(2.).sqrt() / 2.,  Object::from(shape));
         let xs = Intersections::new(vec![intersect1, intersect2]);
         let comp: Computations = xs.data[1].prepare_computations(ray, &xs);
@@ -358,7 +419,7 @@ mod tests_intersection {
         let shape = Sphere::grass(1.);
         let ray = Ray::new(Tuple::point(0., 0., 0.), Tuple::vector(0., 1., 0.));
 
-        let intersect1 = Intersection::new(-1.,  Object::from(shape));
+        let intersect1 = Intersection::new(-1.,  Object::from(shape.clone()));
         let intersect2 = Intersection::new(1.,  Object::from(shape));
         let xs = Intersections::new(vec![intersect1, intersect2]);
         let comp: Computations = xs.data[1].prepare_computations(ray, &xs);
@@ -379,4 +440,117 @@ mod tests_intersection {
         let reflectance = comp.schlick();
         assert_equivalent!(reflectance, 0.48873);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn precomputing_the_surface_coordinates_of_a_hit() {
+        let ray = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+        let intersect = Intersection::new(4., Object::from(Sphere::default()));
+        let xs = Intersections::new(vec![intersect.clone()]);
+        let comps = intersect.prepare_computations(ray, &xs);
+
+        assert_equivalent!(comps.u, 0.);
+        assert_equivalent!(comps.v, 0.5);
+        assert_equivalent!(comps.tangent_v, Tuple::vector(1., 0., 0.));
+        assert_equivalent!(comps.bitangent_v, Tuple::vector(0., 1., 0.));
+    }
+
+    #[test]
+    fn the_tangent_frame_is_orthonormal_on_a_transformed_shape() {
+        let mut shape = Sphere::default();
+        shape.set_transform(Matrix::scaling(Tuple::vector(1., 3., 0.5)) * Matrix::rotation_z(0.4));
+        let ray = Ray::new(Tuple::point(0.3, 0.2, -5.), Tuple::vector(0., 0., 1.));
+        let xs = shape.intersect(ray);
+        let comps = xs.data[0].prepare_computations(ray, &xs);
+
+        assert_equivalent!(comps.tangent_v.length(), 1.);
+        assert_equivalent!(comps.bitangent_v.length(), 1.);
+        assert_equivalent!(comps.tangent_v.dot(comps.normal_v), 0.);
+        assert_equivalent!(comps.bitangent_v.dot(comps.normal_v), 0.);
+        assert_equivalent!(comps.tangent_v.dot(comps.bitangent_v), 0.);
+    }
+
+    #[test]
+    fn the_surface_coordinates_of_a_triangle_hit_are_its_barycentric_weights() {
+        let triangle = Triangle::new(Tuple::point(0., 1., 0.), Tuple::point(-1., 0., 0.), Tuple::point(1., 0., 0.));
+        let ray = Ray::new(Tuple::point(-0.2, 0.3, -5.), Tuple::vector(0., 0., 1.));
+        let xs = triangle.intersect(ray);
+        let comps = xs.data[0].prepare_computations(ray, &xs);
+
+        assert_equivalent!(comps.u, 0.45);
+        assert_equivalent!(comps.v, 0.25);
+        assert_equivalent!(comps.tangent_v, Tuple::vector(-1., -1., 0.).normalize());
+    }
+
+    #[test]
+    fn a_material_normal_map_perturbs_the_shading_normal_only() {
+        let mut plane = Plane::default();
+        plane.material.normal_map = Some(NormalMap::from(Patterns::from(LinearGradient::new(Color::black(), Color::white()))));
+        plane.material.normal_strength = 0.5;
+        let ray = Ray::new(Tuple::point(0.5, 1., 0.5), Tuple::vector(0., -1., 0.));
+        let intersection = Intersection::new(1., Object::from(plane));
+        let comps = intersection.prepare_computations(ray, &Intersections::new(vec![intersection.clone()]));
+
+        assert_equivalent!(comps.normal_v, Tuple::vector(-0.5, 1., 0.).normalize());
+        assert_equivalent!(comps.reflect_v, Tuple::vector(0., -1., 0.).reflect(comps.normal_v));
+        assert_equivalent!(comps.over_point, Tuple::point(0.5, EPSILON, 0.5));
+    }
+
+    #[test]
+    fn a_dispersive_material_bends_each_wavelength_differently() {
+        let mut material = Material::glass();
+        material.dispersion = Some(Dispersion::bk7());
+        let mut sphere = Sphere::default();
+        sphere.set_material(material);
+        let object = Object::from(sphere);
+        let xs = Intersections::new(vec![Intersection::new(4., object.clone()), Intersection::new(6., object)]);
+
+        let blue = xs.data[0].prepare_computations(Ray::spectral(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.), 450.), &xs);
+        let red = xs.data[0].prepare_computations(Ray::spectral(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.), 650.), &xs);
+        let rgb = xs.data[0].prepare_computations(Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.)), &xs);
+
+        assert_eq!(blue.wavelength, Some(450.));
+        assert!(blue.n2 > red.n2);
+        assert_equivalent!(rgb.n2, 1.5);
+        assert_eq!(blue.spawn_ray(blue.under_point, blue.normal_v).wavelength, Some(450.));
+    }
+
+    #[test]
+    fn the_reflectance_without_a_film_is_schlick() {
+        let sphere = Sphere::grass(1.);
+        let ray = Ray::new(Tuple::point(0., 0.99, -2.), Tuple::vector(0., 0., 1.));
+        let xs = Intersections::new(vec![Intersection::new(1.8589, Object::from(sphere))]);
+        let comps = xs.data[0].prepare_computations(ray, &xs);
+
+        assert_equivalent!(comps.reflectance(), Color::new(0.48873, 0.48873, 0.48873));
+    }
+
+    #[test]
+    fn a_thin_film_colours_the_reflectance() {
+        let mut material = Material::glass();
+        material.thin_film = Some(ThinFilm::new(300., 1.33));
+        let mut sphere = Sphere::default();
+        sphere.set_material(material);
+        let ray = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+        let xs = Intersections::new(vec![Intersection::new(4., Object::from(sphere))]);
+        let comps = xs.data[0].prepare_computations(ray, &xs);
+
+        let reflectance = comps.reflectance();
+        assert_equivalent!(reflectance, ThinFilm::new(300., 1.33).reflectance(1., 1., 1.5, None));
+        assert!(!reflectance.red.equivalent(reflectance.green));
+    }
+
+    #[test]
+    fn the_computations_carry_the_material_resolved_at_the_hit() {
+        let mut puddle = Material::phong();
+        puddle.reflective = 0.9;
+        let mut plane = Plane::default();
+        plane.set_material(Material::mix(Material::phong(), puddle.clone(), Patterns::from(Stripe::new(Color::black(), Color::white()))));
+        let object = Object::from(plane);
+        let ray = Ray::new(Tuple::point(1.5, 1., 0.), Tuple::vector(0., -1., 0.));
+        let intersection = Intersection::new(1., object);
+
+        let comps = intersection.prepare_computations(ray, &Intersections::new(vec![intersection.clone()]));
+
+        assert_eq!(*comps.material, puddle);
+    }
+}
@@ -20,4 +20,20 @@ pub mod cube;
 pub mod cylinder;
 pub mod cone;
 pub mod groups;
-pub mod triangle;
\ No newline at end of file
+pub mod triangle;
+pub mod ramp;
+pub mod uv;
+pub mod normal_map;
+pub mod microfacet;
+pub mod sampling;
+pub mod spectrum;
+pub mod conductor;
+pub mod thin_film;
+pub mod cutout;
+pub mod outline;
+pub mod subsurface;
+pub mod medium;
+pub mod volume;
+pub mod occlusion;
+pub mod photon_map;
+pub mod bidirectional;
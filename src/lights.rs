@@ -1,15 +1,107 @@
 use crate::color::Color;
 use crate::tuple::Tuple;
 
+#[derive(Clone, Debug, PartialEq)]
+pub enum LinkTarget {
+    // position of the object in World::objects
+    Id(usize),
+    Name(String)
+}
+
+impl LinkTarget {
+    pub fn matches(&self, id: Option<usize>, name: Option<&str>) -> bool {
+        match self {
+            LinkTarget::Id(target) => id == Some(*target),
+            LinkTarget::Name(target) => name == Some(target.as_str())
+        }
+    }
+}
+
+impl From<usize> for LinkTarget {
+    fn from(id: usize) -> Self {
+        LinkTarget::Id(id)
+    }
+}
+
+impl From<&str> for LinkTarget {
+    fn from(name: &str) -> Self {
+        LinkTarget::Name(name.to_string())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LightLink {
+    All,
+    Include(Vec<LinkTarget>),
+    Exclude(Vec<LinkTarget>)
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LightShape {
+    Point,
+    // a parallelogram centred on the light's position with full edges u and v, emitting on the side of u x v
+    Area { u: Tuple, v: Tuple }
+}
+
+// shade_hit lights an area light as a point at its centre, the bidirectional integrator samples its surface
+#[derive(Clone, Debug, PartialEq)]
 pub struct Light {
     pub position: Tuple,
-    pub intensity: Color
+    pub intensity: Color,
+    pub link: LightLink,
+    pub shape: LightShape
 }
 
 impl Light {
     pub fn point_light(position: Tuple, intensity: Color) -> Self {
-        Light { position, intensity }
+        Light { position, intensity, link: LightLink::All, shape: LightShape::Point }
+    }
+
+    pub fn area_light(centre: Tuple, u: Tuple, v: Tuple, intensity: Color) -> Self {
+        if !u.is_vector() || !v.is_vector() || u.cross(v).length() <= 0. {
+            panic!("Invalid args. u and v must be non parallel vectors")
+        }
+        Light { position: centre, intensity, link: LightLink::All, shape: LightShape::Area { u, v } }
+    }
+
+    pub fn area(&self) -> f64 {
+        match self.shape {
+            LightShape::Point => 0.,
+            LightShape::Area { u, v } => u.cross(v).length()
+        }
+    }
+
+    pub fn normal(&self) -> Option<Tuple> {
+        match self.shape {
+            LightShape::Point => None,
+            LightShape::Area { u, v } => Some(u.cross(v).normalize())
+        }
+    }
+
+    // u1 and u2 are uniform in [0, 1)
+    pub fn sample_point(&self, u1: f64, u2: f64) -> Tuple {
+        match self.shape {
+            LightShape::Point => self.position,
+            LightShape::Area { u, v } => self.position + u * (u1 - 0.5) + v * (u2 - 0.5)
+        }
+    }
+
+    pub fn including(mut self, targets: Vec<LinkTarget>) -> Self {
+        self.link = LightLink::Include(targets);
+        self
+    }
+
+    pub fn excluding(mut self, targets: Vec<LinkTarget>) -> Self {
+        self.link = LightLink::Exclude(targets);
+        self
+    }
+
+    pub fn illuminates(&self, id: Option<usize>, name: Option<&str>) -> bool {
+        match &self.link {
+            LightLink::All => true,
+            LightLink::Include(targets) => targets.iter().any(|target| target.matches(id, name)),
+            LightLink::Exclude(targets) => !targets.iter().any(|target| target.matches(id, name))
+        }
     }
 }
 
@@ -29,4 +121,43 @@ mod tests_lights {
         assert_equivalent!(light.position, position);
         assert_equivalent!(light.intensity, intensity);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn a_light_illuminates_everything_by_default() {
+        let light = Light::point_light(Tuple::point(0., 0., 0.), Color::white());
+
+        assert!(light.illuminates(None, None));
+        assert!(light.illuminates(Some(3), Some("floor")));
+    }
+
+    #[test]
+    fn an_including_light_only_reaches_its_targets() {
+        let light = Light::point_light(Tuple::point(0., 0., 0.), Color::white())
+            .including(vec![LinkTarget::from("hero"), LinkTarget::from(2)]);
+
+        assert!(light.illuminates(Some(0), Some("hero")));
+        assert!(light.illuminates(Some(2), None));
+        assert!(!light.illuminates(Some(1), Some("floor")));
+    }
+
+    #[test]
+    fn an_excluding_light_skips_its_targets() {
+        let light = Light::point_light(Tuple::point(0., 0., 0.), Color::white())
+            .excluding(vec![LinkTarget::from("background")]);
+
+        assert!(!light.illuminates(Some(0), Some("background")));
+        assert!(light.illuminates(Some(0), Some("hero")));
+        assert!(light.illuminates(Some(0), None));
+    }
+
+    #[test]
+    fn an_area_light_spans_a_parallelogram_around_its_centre() {
+        let light = Light::area_light(Tuple::point(0., 5., 0.), Tuple::vector(2., 0., 0.), Tuple::vector(0., 0., -1.), Color::white());
+
+        assert_equivalent!(light.area(), 2.);
+        assert_equivalent!(light.normal().unwrap(), Tuple::vector(0., 1., 0.));
+        assert_equivalent!(light.sample_point(0.5, 0.5), Tuple::point(0., 5., 0.));
+        assert_equivalent!(light.sample_point(0., 1.), Tuple::point(-1., 5., -0.5));
+        assert_eq!(Light::point_light(Tuple::point(0., 0., 0.), Color::white()).normal(), None);
+    }
+}
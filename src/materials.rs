@@ -1,11 +1,122 @@
+use std::borrow::Cow;
+use std::f64::consts::PI;
 use crate::color::Color;
+use crate::conductor::Conductor;
+use crate::cutout::Cutout;
+use crate::equivalent::Equivalence;
 use crate::lights::Light;
+use crate::medium::Medium;
+use crate::microfacet::{cook_torrance, ggx_distribution, smith_geometry};
+use crate::normal_map::NormalMap;
 use crate::object::Object;
 use crate::patterns::{Incuse, Patterns};
+use crate::ramp::{ColorRamp, Interpolation};
+use crate::spectrum::Dispersion;
+use crate::subsurface::Subsurface;
+use crate::thin_film::ThinFilm;
 use crate::tuple::Tuple;
 
+#[derive(Debug, PartialEq, Clone)]
+pub enum ShadingModel {
+    Phong,
+    Microfacet,
+    Toon(Toon)
+}
+
+// a clear dielectric layer over the rest of the material
 #[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Clearcoat {
+    pub ior: f64,
+    pub roughness: f64
+}
+
+impl Clearcoat {
+    pub fn new(ior: f64, roughness: f64) -> Self {
+        Clearcoat { ior, roughness }
+    }
+
+    pub fn fresnel(&self, cos: f64) -> f64 {
+        let r0 = ((self.ior - 1.) / (self.ior + 1.)).powi(2);
+        r0 + (1. - r0) * (1. - cos.clamp(0., 1.)).powi(5)
+    }
+
+    // the base only receives what the coat lets through towards the eye
+    pub fn lighting(&self, base: Color, light: &Light, point: Tuple, eye_vector: Tuple, normal_vector: Tuple, transmission: Color) -> Color {
+        let n_dot_v = eye_vector.dot(normal_vector);
+        let attenuated = base * (1. - self.fresnel(n_dot_v));
+
+        let light_vector = (light.position - point).normalize();
+        let n_dot_l = light_vector.dot(normal_vector);
+        if transmission == Color::black() || n_dot_l <= 0. || n_dot_v <= 0. {
+            return attenuated;
+        }
+
+        let half_vector = (light_vector + eye_vector).normalize();
+        let distribution = ggx_distribution(normal_vector, half_vector, self.roughness);
+        let geometry = smith_geometry(normal_vector, light_vector, eye_vector, self.roughness);
+        let specular = self.fresnel(half_vector.dot(eye_vector)) * distribution * geometry / (4. * n_dot_l * n_dot_v);
+        attenuated + light.intensity * transmission * (specular * PI * n_dot_l)
+    }
+}
+
+// cel shading, n.l is looked up in the ramp instead of scaling the diffuse smoothly
+#[derive(Debug, PartialEq, Clone)]
+pub struct Toon {
+    pub ramp: ColorRamp,
+    pub specular_threshold: f64,
+    pub rim_color: Color,
+    // how far in from the silhouette the rim reaches, as 1 - n.v, 0 turns it off
+    pub rim_width: f64
+}
+
+impl Toon {
+    pub fn new(ramp: ColorRamp) -> Self {
+        Toon { ramp, specular_threshold: 0.5, rim_color: Color::white(), rim_width: 0. }
+    }
+
+    // evenly spaced hard steps from black to white
+    pub fn bands(count: usize) -> Self {
+        if count < 2 {
+            panic!("Invalid args. Toon needs at least two bands")
+        }
+        let stops = (0..count)
+            .map(|i| {
+                let level = i as f64 / (count - 1) as f64;
+                (i as f64 / count as f64, Color::new(level, level, level))
+            })
+            .collect();
+        Toon::new(ColorRamp::new(stops, Interpolation::Constant))
+    }
+
+    pub fn with_specular_threshold(mut self, threshold: f64) -> Self {
+        self.specular_threshold = threshold;
+        self
+    }
+
+    pub fn with_rim(mut self, color: Color, width: f64) -> Self {
+        self.rim_color = color;
+        self.rim_width = width;
+        self
+    }
+}
+
+impl Default for Toon {
+    fn default() -> Self {
+        Toon::bands(3)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct MaterialMix {
+    pub material_a: Material,
+    pub material_b: Material,
+    // the grayscale of the mask is the weight of material_b
+    pub mask: Patterns
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub struct Material {
+    pub model: ShadingModel,
     pub color: Color,
     pub ambient: f64,
     pub diffuse: f64,
@@ -14,12 +125,32 @@ pub struct Material {
     pub reflective: f64,
     pub transparency: f64,
     pub reflactive_index: f64,
-    pub pattern: Option<Patterns>
+    pub pattern: Option<Patterns>,
+    pub normal_map: Option<NormalMap>,
+    pub normal_strength: f64,
+    pub metallic: f64,
+    pub roughness: f64,
+    pub glossy_samples: usize,
+    pub absorption_color: Color,
+    pub absorption_density: f64,
+    pub dispersion: Option<Dispersion>,
+    pub transparent_shadows: bool,
+    pub conductor: Option<Conductor>,
+    pub thin_film: Option<ThinFilm>,
+    pub clearcoat: Option<Clearcoat>,
+    pub mix: Option<Box<MaterialMix>>,
+    pub cutout: Option<Cutout>,
+    pub subsurface: Option<Subsurface>,
+    // fills the interior, seen by rays travelling inside a transparent object
+    pub medium: Option<Medium>,
+    // radiance given off by the outside of the surface, the side its normal points to
+    pub emission: Color
 }
 
 impl Material {
     pub fn phong() -> Self {
         Material {
+            model: ShadingModel::Phong,
             color: Color::white(),
             ambient: 0.1,
             diffuse: 0.9,
@@ -28,40 +159,148 @@ impl Material {
             reflective: 0.,
             transparency: 0.,
             reflactive_index: 1.,
-            pattern: None
+            pattern: None,
+            normal_map: None,
+            normal_strength: 1.,
+            metallic: 0.,
+            roughness: 0.,
+            glossy_samples: 16,
+            absorption_color: Color::white(),
+            absorption_density: 0.,
+            dispersion: None,
+            transparent_shadows: false,
+            conductor: None,
+            thin_film: None,
+            clearcoat: None,
+            mix: None,
+            cutout: None,
+            subsurface: None,
+            medium: None,
+            emission: Color::black()
         }
     }
 
+    pub fn pbr(color: Color, metallic: f64, roughness: f64) -> Self {
+        let mut material = Material::phong();
+        material.model = ShadingModel::Microfacet;
+        material.color = color;
+        material.metallic = metallic;
+        material.roughness = roughness;
+        material
+    }
+
+    pub fn toon(color: Color, toon: Toon) -> Self {
+        let mut material = Material::phong();
+        material.model = ShadingModel::Toon(toon);
+        material.color = color;
+        material
+    }
+
     pub fn glass() -> Self {
         let mut material = Material::phong();
         material.transparency = 1.;
         material.reflective = 1.;
         material.reflactive_index = 1.5;
+        material.transparent_shadows = true;
+        material
+    }
+
+    // the base color is the reflectance at normal incidence so highlights match the mirrored color
+    pub fn metal(conductor: Conductor) -> Self {
+        let mut material = Material::pbr(conductor.reflectance(1.), 1., 0.);
+        material.conductor = Some(conductor);
+        material
+    }
+
+    // unresolved lookups, like the refractive index of a container, fall back to material_a
+    pub fn mix(material_a: Material, material_b: Material, mask: Patterns) -> Self {
+        let mut material = material_a.clone();
+        material.mix = Some(Box::new(MaterialMix { material_a, material_b, mask }));
         material
     }
 
-    pub fn lighting(&self, object: Object, light: Light, point: Tuple, eye_vector: Tuple, normal_vector: Tuple, in_shadow: bool) -> Color {
+    // the material that applies at a point, with any mixes blended down to a single material, only a blend allocates
+    pub fn at(&self, object: &Object, point: Tuple) -> Cow<'_, Material> {
+        let mix = match &self.mix {
+            Some(mix) => mix,
+            None => return Cow::Borrowed(self)
+        };
+        let mask = mix.mask.color_at_object(object, point);
+        let weight = ((mask.red + mask.green + mask.blue) / 3.).clamp(0., 1.);
+        let material_a = mix.material_a.at(object, point);
+        let material_b = mix.material_b.at(object, point);
+        if weight <= 0. {
+            return material_a;
+        }
+        if weight >= 1. {
+            return material_b;
+        }
+        let lerp = |a: f64, b: f64| a * (1. - weight) + b * weight;
+        let lerp_color = |a: Color, b: Color| a * (1. - weight) + b * weight;
+        let dominant = if weight < 0.5 { &material_a } else { &material_b };
+
+        Cow::Owned(Material {
+            color: lerp_color(material_a.color_at(object, point), material_b.color_at(object, point)),
+            pattern: None,
+            ambient: lerp(material_a.ambient, material_b.ambient),
+            diffuse: lerp(material_a.diffuse, material_b.diffuse),
+            specular: lerp(material_a.specular, material_b.specular),
+            shininess: lerp(material_a.shininess, material_b.shininess),
+            reflective: lerp(material_a.reflective, material_b.reflective),
+            transparency: lerp(material_a.transparency, material_b.transparency),
+            reflactive_index: lerp(material_a.reflactive_index, material_b.reflactive_index),
+            normal_strength: lerp(material_a.normal_strength, material_b.normal_strength),
+            metallic: lerp(material_a.metallic, material_b.metallic),
+            roughness: lerp(material_a.roughness, material_b.roughness),
+            absorption_color: lerp_color(material_a.absorption_color, material_b.absorption_color),
+            absorption_density: lerp(material_a.absorption_density, material_b.absorption_density),
+            mix: None,
+            ..dominant.as_ref().clone()
+        })
+    }
+
+    pub fn lighting(&self, object: &Object, light: &Light, point: Tuple, eye_vector: Tuple, normal_vector: Tuple, in_shadow: bool) -> Color {
+        let transmission = if in_shadow { Color::black() } else { Color::white() };
+        self.lighting_through(object, light, point, eye_vector, normal_vector, transmission)
+    }
+
+    // transmission is how much of the light reaches the point past whatever lies in between, ambient is never shadowed
+    pub fn lighting_through(&self, object: &Object, light: &Light, point: Tuple, eye_vector: Tuple, normal_vector: Tuple, transmission: Color) -> Color {
+        let base = self.base_lighting(object, light, point, eye_vector, normal_vector, transmission);
+        match self.clearcoat {
+            Some(coat) => coat.lighting(base, light, point, eye_vector, normal_vector, transmission),
+            None => base
+        }
+    }
+
+    fn base_lighting(&self, object: &Object, light: &Light, point: Tuple, eye_vector: Tuple, normal_vector: Tuple, transmission: Color) -> Color {
         if !point.is_point() || !eye_vector.is_vector() || !normal_vector.is_vector() {
             panic!("Invalid args. point = Tuple::point, eye_vector = Tuple::vector, normal_vector = Tuple::vector")
         }
 
         let difuse;
         let specular;
-        let mut color = self.color;
-        if let Some(pattern) = self.pattern {
-            color = pattern.color_at_object(object, point);
-        }
+        let color = self.color_at(object, point);
 
-        let effective_color = color * light.intensity;
-        let light_vector = (light.position - point).normalize();
-
-        let ambient_light = effective_color * self.ambient;
+        let ambient_light = color * light.intensity * self.ambient;
 
-        if in_shadow {
+        if transmission == Color::black() {
             return ambient_light;
         }
 
+        let light = Light { intensity: light.intensity * transmission, ..light.clone() };
+        let effective_color = color * light.intensity;
+        let light_vector = (light.position - point).normalize();
+
         let light_dot_normal = light_vector.dot(normal_vector);
+        if self.model == ShadingModel::Microfacet {
+            // point lights are scaled so a white lambertian surface facing the light matches phong's diffuse
+            let brdf = cook_torrance(color, self.metallic, self.roughness, light_vector, eye_vector, normal_vector);
+            return ambient_light + brdf * light.intensity * (PI * light_dot_normal.max(0.));
+        }
+        if let ShadingModel::Toon(ref toon) = self.model {
+            return ambient_light + self.toon_lighting(toon, effective_color, light.intensity, light_vector, eye_vector, normal_vector);
+        }
         if light_dot_normal < 0. {
             difuse = Color::black();
             specular = Color::black();
@@ -79,6 +318,64 @@ impl Material {
         }
         ambient_light + difuse + specular
     }
+
+    fn toon_lighting(&self, toon: &Toon, effective_color: Color, intensity: Color, light_vector: Tuple, eye_vector: Tuple, normal_vector: Tuple) -> Color {
+        let light_dot_normal = light_vector.dot(normal_vector);
+        let diffuse = effective_color * toon.ramp.color_at(light_dot_normal.max(0.)) * self.diffuse;
+
+        let reflect_dot_eye = (-light_vector).reflect(normal_vector).dot(eye_vector);
+        let highlight = light_dot_normal > 0. && reflect_dot_eye > 0. && reflect_dot_eye.powf(self.shininess) >= toon.specular_threshold;
+        let specular = if highlight { intensity * self.specular } else { Color::black() };
+
+        let on_rim = toon.rim_width > 0. && 1. - eye_vector.dot(normal_vector) >= 1. - toon.rim_width;
+        let rim = if on_rim { toon.rim_color * intensity } else { Color::black() };
+        diffuse + specular + rim
+    }
+
+    // transparent and bending the light, a refractive index of 1 lets it straight through
+    pub fn refracts(&self) -> bool {
+        self.transparency > 0. && !self.reflactive_index.equivalent(1.)
+    }
+
+    // Beer-Lambert, absorption_color is what white light fades towards as it travels through the material
+    pub fn transmittance(&self, distance: f64) -> Color {
+        let absorb = |channel: f64| (-(1. - channel) * self.absorption_density * distance).exp();
+        Color::new(
+            absorb(self.absorption_color.red),
+            absorb(self.absorption_color.green),
+            absorb(self.absorption_color.blue)
+        )
+    }
+
+    pub fn refractive_index_at(&self, wavelength: Option<f64>) -> f64 {
+        match (self.dispersion, wavelength) {
+            (Some(dispersion), Some(wavelength)) => dispersion.refractive_index(wavelength),
+            _ => self.reflactive_index
+        }
+    }
+
+    pub fn color_at(&self, object: &Object, point: Tuple) -> Color {
+        match &self.pattern {
+            Some(pattern) => pattern.color_at_object(object, point),
+            None => self.color
+        }
+    }
+
+    pub fn brdf(&self, object: &Object, point: Tuple, light_vector: Tuple, eye_vector: Tuple, normal_vector: Tuple) -> Color {
+        let color = self.color_at(object, point);
+        match self.model {
+            ShadingModel::Microfacet => cook_torrance(color, self.metallic, self.roughness, light_vector, eye_vector, normal_vector),
+            ShadingModel::Phong | ShadingModel::Toon(_) => {
+                if light_vector.dot(normal_vector) <= 0. || eye_vector.dot(normal_vector) <= 0. {
+                    return Color::black();
+                }
+                let diffuse = color * (self.diffuse / PI);
+                let reflect_dot_eye = (-light_vector).reflect(normal_vector).dot(eye_vector).max(0.);
+                let specular = self.specular * (self.shininess + 2.) / (2. * PI) * reflect_dot_eye.powf(self.shininess);
+                diffuse + Color::white() * specular
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -86,7 +383,7 @@ mod tests_lights {
     use crate::assert_equivalent;
     use crate::equivalent::Equivalence;
     use crate::lights::Light;
-    use crate::patterns::Stripe;
+    use crate::patterns::{LinearGradient, Stripe};
     use crate::sphere::Sphere;
     use super::*;
 
@@ -114,7 +411,7 @@ mod tests_lights {
             Color::white()
         );
 
-        let result = material.lighting(Object::from(Sphere::default()), light, position, eye_vector, normal_v, false);
+        let result = material.lighting(&Object::from(Sphere::default()), &light, position, eye_vector, normal_v, false);
 
         assert_equivalent!(result, Color::new(1.9, 1.9, 1.9));
     }
@@ -132,7 +429,7 @@ mod tests_lights {
             Color::white()
         );
 
-        let result = material.lighting(Object::from(Sphere::default()), light, position, eye_vector, normal_v, false);
+        let result = material.lighting(&Object::from(Sphere::default()), &light, position, eye_vector, normal_v, false);
 
         assert_equivalent!(result, Color::new(1.0, 1.0, 1.0));
     }
@@ -150,7 +447,7 @@ mod tests_lights {
             Color::white()
         );
 
-        let result = material.lighting(Object::from(Sphere::default()), light, position, eye_vector, normal_v, false);
+        let result = material.lighting(&Object::from(Sphere::default()), &light, position, eye_vector, normal_v, false);
 
         assert_equivalent!(result, Color::new(0.7364, 0.7364, 0.7364));
     }
@@ -168,7 +465,7 @@ mod tests_lights {
             Color::white()
         );
 
-        let result = material.lighting(Object::from(Sphere::default()), light, position, eye_vector, normal_v, false);
+        let result = material.lighting(&Object::from(Sphere::default()), &light, position, eye_vector, normal_v, false);
 
         assert_equivalent!(result, Color::new(1.6364, 1.6364, 1.6364));
     }
@@ -186,7 +483,7 @@ mod tests_lights {
             Color::white()
         );
 
-        let result = material.lighting(Object::from(Sphere::default()), light, position, eye_vector, normal_v, false);
+        let result = material.lighting(&Object::from(Sphere::default()), &light, position, eye_vector, normal_v, false);
 
         assert_equivalent!(result, Color::new(0.1, 0.1, 0.1));
     }
@@ -202,7 +499,7 @@ mod tests_lights {
         let light = Light::point_light(Tuple::point(0., 0., -10.), Color::new(1., 1., 1.));
         let in_shadow = true;
 
-        let result = material.lighting(Object::from(Sphere::default()), light, position, eye_v, normal_v, in_shadow);
+        let result = material.lighting(&Object::from(Sphere::default()), &light, position, eye_v, normal_v, in_shadow);
 
         assert_eq!(result, Color::new(0.1, 0.1, 0.1));
     }
@@ -220,8 +517,8 @@ mod tests_lights {
 
         let light = Light::point_light(Tuple::point(0., 0., -10.), Color::white());
 
-        let c1 = material.lighting(Object::from(Sphere::default()), light, Tuple::point(0.9, 0., 0.), eye_v, normal_v, false);
-        let c2 = material.lighting(Object::from(Sphere::default()), light, Tuple::point(1.1, 0., 0.), eye_v, normal_v, false);
+        let c1 = material.lighting(&Object::from(Sphere::default()), &light, Tuple::point(0.9, 0., 0.), eye_v, normal_v, false);
+        let c2 = material.lighting(&Object::from(Sphere::default()), &light, Tuple::point(1.1, 0., 0.), eye_v, normal_v, false);
 
         assert_eq!(c1, Color::white());
         assert_eq!(c2, Color::black());
@@ -239,4 +536,252 @@ mod tests_lights {
         assert_eq!(material.transparency, 0.);
         assert_eq!(material.reflactive_index, 1.);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn a_pbr_material_facing_the_light_matches_phong_diffuse() {
+        let material = Material::pbr(Color::new(1., 0.5, 0.2), 0., 1.);
+        let eye_v = Tuple::vector(0., 0., -1.);
+        let normal_v = Tuple::vector(0., 0., -1.);
+        let light = Light::point_light(Tuple::point(0., 0., -10.), Color::white());
+
+        let result = material.lighting(&Object::from(Sphere::default()), &light, Tuple::point(0., 0., 0.), eye_v, normal_v, false);
+
+        assert!((result.red - 1.1).abs() < 0.1);
+        assert!((result.green - 0.55).abs() < 0.05);
+    }
+
+    #[test]
+    fn a_smooth_metal_has_a_tight_highlight() {
+        let material = Material::pbr(Color::white(), 1., 0.2);
+        let normal_v = Tuple::vector(0., 0., -1.);
+        let light = Light::point_light(Tuple::point(0., 0., -10.), Color::white());
+        let object = Object::from(Sphere::default());
+
+        let on_axis = material.lighting(&object, &light, Tuple::point(0., 0., 0.), Tuple::vector(0., 0., -1.), normal_v, false);
+        let off_axis = material.lighting(&object, &light, Tuple::point(0., 0., 0.), Tuple::vector(0., 1., -1.).normalize(), normal_v, false);
+
+        assert!(on_axis.red > 10.);
+        assert!(off_axis.red < 0.2);
+    }
+
+    #[test]
+    fn a_pbr_material_in_shadow_only_gets_ambient() {
+        let material = Material::pbr(Color::white(), 0.5, 0.5);
+        let normal_v = Tuple::vector(0., 0., -1.);
+        let light = Light::point_light(Tuple::point(0., 0., -10.), Color::white());
+
+        let result = material.lighting(&Object::from(Sphere::default()), &light, Tuple::point(0., 0., 0.), normal_v, normal_v, true);
+
+        assert_equivalent!(result, Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn the_brdf_of_a_phong_material_is_lambertian_without_specular() {
+        let mut material = Material::phong();
+        material.specular = 0.;
+        let normal_v = Tuple::vector(0., 1., 0.);
+
+        let result = material.brdf(&Object::from(Sphere::default()), Tuple::point(0., 1., 0.), Tuple::vector(1., 1., 0.).normalize(), normal_v, normal_v);
+
+        assert_equivalent!(result, Color::white() * (0.9 / PI));
+    }
+
+    #[test]
+    fn the_default_material_absorbs_nothing() {
+        let material = Material::phong();
+
+        assert_equivalent!(material.transmittance(100.), Color::white());
+    }
+
+    #[test]
+    fn absorption_grows_with_the_distance_travelled() {
+        let mut material = Material::glass();
+        material.absorption_color = Color::new(1., 0.5, 0.);
+        material.absorption_density = 2.;
+
+        let thin = material.transmittance(0.5);
+        let thick = material.transmittance(1.);
+
+        assert_equivalent!(thin, Color::new(1., (-0.5f64).exp(), (-1f64).exp()));
+        assert_equivalent!(thick, Color::new(1., (-1f64).exp(), (-2f64).exp()));
+    }
+
+    #[test]
+    fn dispersion_only_applies_to_rays_carrying_a_wavelength() {
+        let mut material = Material::glass();
+        material.dispersion = Some(Dispersion::cauchy(1.5046, 0.0042));
+
+        assert_equivalent!(material.refractive_index_at(None), 1.5);
+        assert_equivalent!(material.refractive_index_at(Some(500.)), 1.5214);
+        assert_equivalent!(Material::glass().refractive_index_at(Some(500.)), 1.5);
+    }
+
+    #[test]
+    fn lighting_through_a_partial_blocker_keeps_the_ambient_term() {
+        let material = Material::phong();
+        let eye_v = Tuple::vector(0., 0., -1.);
+        let normal_v = Tuple::vector(0., 0., -1.);
+        let light = Light::point_light(Tuple::point(0., 0., -10.), Color::white());
+        let object = Object::from(Sphere::default());
+
+        let tinted = material.lighting_through(&object, &light, Tuple::point(0., 0., 0.), eye_v, normal_v, Color::new(1., 0.5, 0.));
+
+        assert_equivalent!(tinted, Color::new(1.9, 0.1 + 0.9 * 0.5 + 0.9 * 0.5, 0.1));
+    }
+
+    #[test]
+    fn a_clearcoat_dims_the_base_by_its_fresnel_term() {
+        let mut material = Material::phong();
+        material.specular = 0.;
+        let light = Light::point_light(Tuple::point(0., 0., -10.), Color::white());
+        let object = Object::from(Sphere::default());
+        let eye_v = Tuple::vector(0., 1., -1.).normalize();
+        let normal_v = Tuple::vector(0., 0., -1.);
+
+        let bare = material.lighting(&object, &light, Tuple::point(0., 0., 0.), eye_v, normal_v, false);
+        material.clearcoat = Some(Clearcoat::new(1.5, 0.1));
+        let coated = material.lighting(&object, &light, Tuple::point(0., 0., 0.), eye_v, normal_v, false);
+
+        let coat = Clearcoat::new(1.5, 0.1);
+        assert!(coated.red < bare.red);
+        assert!(coated.red > bare.red * (1. - coat.fresnel(eye_v.dot(normal_v))) - 0.0001);
+    }
+
+    #[test]
+    fn a_clearcoat_adds_a_white_highlight_over_a_coloured_base() {
+        let mut material = Material::pbr(Color::new(0.8, 0., 0.), 0., 0.8);
+        let light = Light::point_light(Tuple::point(0., 0., -10.), Color::white());
+        let object = Object::from(Sphere::default());
+        let normal_v = Tuple::vector(0., 0., -1.);
+
+        material.clearcoat = Some(Clearcoat::new(1.5, 0.05));
+        let coated = material.lighting(&object, &light, Tuple::point(0., 0., 0.), normal_v, normal_v, false);
+
+        assert!(coated.green > 1.);
+        assert!(coated.red > coated.green);
+    }
+
+    #[test]
+    fn a_clearcoat_in_shadow_only_passes_the_ambient_through() {
+        let mut material = Material::phong();
+        material.clearcoat = Some(Clearcoat::new(1.5, 0.1));
+        let light = Light::point_light(Tuple::point(0., 0., -10.), Color::white());
+        let normal_v = Tuple::vector(0., 0., -1.);
+
+        let result = material.lighting(&Object::from(Sphere::default()), &light, Tuple::point(0., 0., 0.), normal_v, normal_v, true);
+
+        assert_equivalent!(result, Color::new(0.096, 0.096, 0.096));
+    }
+
+    #[test]
+    fn a_mix_picks_each_material_where_the_mask_is_solid() {
+        let mut rust = Material::phong();
+        rust.color = Color::new(0.5, 0.2, 0.1);
+        rust.specular = 0.;
+        let metal = Material::metal(Conductor::iron());
+        let material = Material::mix(metal.clone(), rust.clone(), Patterns::from(Stripe::new(Color::black(), Color::white())));
+        let object = Object::from(Sphere::default());
+
+        assert_eq!(*material.at(&object, Tuple::point(0.5, 0., 0.)), metal);
+        assert_eq!(*material.at(&object, Tuple::point(1.5, 0., 0.)), rust);
+    }
+
+    #[test]
+    fn only_a_blended_mix_copies_the_material() {
+        let object = Object::from(Sphere::default());
+        let mix = Material::mix(Material::phong(), Material::glass(), Patterns::from(LinearGradient::new(Color::black(), Color::white())));
+
+        assert!(matches!(Material::phong().at(&object, Tuple::point(0.25, 0., 0.)), Cow::Borrowed(_)));
+        assert!(matches!(mix.at(&object, Tuple::point(0.25, 0., 0.)), Cow::Owned(_)));
+    }
+
+    #[test]
+    fn a_mix_blends_the_numeric_properties_by_the_mask() {
+        let mut matte = Material::phong();
+        matte.color = Color::new(1., 0., 0.);
+        let mut puddle = Material::phong();
+        puddle.color = Color::new(0., 0., 1.);
+        puddle.reflective = 0.8;
+        puddle.conductor = Some(Conductor::silver());
+        let material = Material::mix(matte, puddle, Patterns::from(LinearGradient::new(Color::black(), Color::white())));
+        let object = Object::from(Sphere::default());
+
+        let blended = material.at(&object, Tuple::point(0.25, 0., 0.));
+
+        assert_equivalent!(blended.color, Color::new(0.75, 0., 0.25));
+        assert_equivalent!(blended.reflective, 0.2);
+        assert_eq!(blended.conductor, None);
+        assert_eq!(material.at(&object, Tuple::point(0.75, 0., 0.)).conductor, Some(Conductor::silver()));
+    }
+
+    #[test]
+    fn mixes_can_be_nested() {
+        let mut red = Material::phong();
+        red.color = Color::new(1., 0., 0.);
+        let mut green = Material::phong();
+        green.color = Color::new(0., 1., 0.);
+        let inner = Material::mix(red, green, Patterns::from(Stripe::new(Color::black(), Color::white())));
+        let material = Material::mix(inner, Material::phong(), Patterns::from(Stripe::new(Color::black(), Color::black())));
+        let object = Object::from(Sphere::default());
+
+        let resolved = material.at(&object, Tuple::point(1.5, 0., 0.));
+
+        assert_equivalent!(resolved.color, Color::new(0., 1., 0.));
+        assert_eq!(resolved.mix, None);
+    }
+
+    #[test]
+    fn toon_bands_are_hard_steps() {
+        let toon = Toon::bands(2);
+
+        assert_equivalent!(toon.ramp.color_at(0.49), Color::black());
+        assert_equivalent!(toon.ramp.color_at(0.5), Color::white());
+        assert_equivalent!(Toon::default().ramp.color_at(0.4), Color::new(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn toon_diffuse_is_quantized() {
+        let material = Material { specular: 0., ..Material::toon(Color::white(), Toon::bands(3)) };
+        let object = Object::from(Sphere::default());
+        let normal_v = Tuple::vector(0., 0., -1.);
+        let light_at = |x: f64| Light::point_light(Tuple::point(x, 0., -1.), Color::white());
+
+        // n.l = 0.4 and 0.5 fall in the same band
+        let a = material.lighting(&object, &light_at(2.291), Tuple::point(0., 0., 0.), normal_v, normal_v, false);
+        let b = material.lighting(&object, &light_at(1.732), Tuple::point(0., 0., 0.), normal_v, normal_v, false);
+        let c = material.lighting(&object, &light_at(0.), Tuple::point(0., 0., 0.), normal_v, normal_v, false);
+
+        assert_equivalent!(a, Color::new(0.55, 0.55, 0.55));
+        assert_equivalent!(b, a);
+        assert_equivalent!(c, Color::new(1., 1., 1.));
+    }
+
+    #[test]
+    fn toon_specular_switches_on_at_the_threshold() {
+        let material = Material::toon(Color::white(), Toon::bands(2).with_specular_threshold(0.9));
+        let object = Object::from(Sphere::default());
+        let normal_v = Tuple::vector(0., 0., -1.);
+        let light = Light::point_light(Tuple::point(0., 0., -10.), Color::white());
+
+        let on_axis = material.lighting(&object, &light, Tuple::point(0., 0., 0.), Tuple::vector(0., 0., -1.), normal_v, false);
+        let off_axis = material.lighting(&object, &light, Tuple::point(0., 0., 0.), Tuple::vector(0., 0.1, -1.).normalize(), normal_v, false);
+
+        assert_equivalent!(on_axis, Color::new(1.9, 1.9, 1.9));
+        assert_equivalent!(off_axis, Color::new(1., 1., 1.));
+    }
+
+    #[test]
+    fn toon_rim_light_outlines_the_silhouette() {
+        let toon = Toon::bands(2).with_rim(Color::new(0., 0., 1.), 0.6);
+        let material = Material { specular: 0., ..Material::toon(Color::white(), toon) };
+        let object = Object::from(Sphere::default());
+        let normal_v = Tuple::vector(0., 0., -1.);
+        let light = Light::point_light(Tuple::point(0., 0., -10.), Color::white());
+
+        let facing = material.lighting(&object, &light, Tuple::point(0., 0., 0.), normal_v, normal_v, false);
+        let grazing = material.lighting(&object, &light, Tuple::point(0., 0., 0.), Tuple::vector(1., 0., -0.5).normalize(), normal_v, false);
+
+        assert_equivalent!(facing, Color::new(1., 1., 1.));
+        assert_equivalent!(grazing, Color::new(1., 1., 2.));
+    }
+}
@@ -0,0 +1,112 @@
+use std::f64::consts::PI;
+use crate::color::Color;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum PhaseFunction {
+    Isotropic,
+    // g in (-1, 1), positive scatters forward
+    HenyeyGreenstein(f64)
+}
+
+impl PhaseFunction {
+    // cos is taken between the direction light travels before and after scattering
+    pub fn evaluate(&self, cos: f64) -> f64 {
+        match *self {
+            PhaseFunction::Isotropic => 1. / (4. * PI),
+            PhaseFunction::HenyeyGreenstein(g) => {
+                let denominator = 1. + g * g - 2. * g * cos;
+                (1. - g * g) / (4. * PI * denominator * denominator.sqrt())
+            }
+        }
+    }
+}
+
+// coefficients are per world unit
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Medium {
+    pub absorption: Color,
+    pub scattering: Color,
+    pub phase: PhaseFunction,
+    // ray marching steps for in-scattered light
+    pub samples: usize,
+    // rays that escape the scene only gather light this far
+    pub max_distance: f64
+}
+
+impl Medium {
+    pub fn new(absorption: Color, scattering: Color) -> Self {
+        Medium { absorption, scattering, phase: PhaseFunction::Isotropic, samples: 16, max_distance: 100. }
+    }
+
+    // a grey medium that only scatters
+    pub fn fog(density: f64) -> Self {
+        Medium::new(Color::black(), Color::white() * density)
+    }
+
+    pub fn with_phase(mut self, phase: PhaseFunction) -> Self {
+        self.phase = phase;
+        self
+    }
+
+    pub fn with_samples(mut self, samples: usize) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    pub fn with_max_distance(mut self, max_distance: f64) -> Self {
+        self.max_distance = max_distance;
+        self
+    }
+
+    pub fn extinction(&self) -> Color {
+        self.absorption + self.scattering
+    }
+
+    pub fn transmittance(&self, distance: f64) -> Color {
+        let extinction = self.extinction();
+        Color::new(
+            (-extinction.red * distance).exp(),
+            (-extinction.green * distance).exp(),
+            (-extinction.blue * distance).exp()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests_medium {
+    use crate::assert_equivalent;
+    use crate::equivalent::Equivalence;
+    use super::*;
+
+    fn integrate_over_the_sphere(phase: PhaseFunction) -> f64 {
+        let steps = 10000;
+        (0..steps)
+            .map(|i| {
+                let theta = (i as f64 + 0.5) / steps as f64 * PI;
+                phase.evaluate(theta.cos()) * 2. * PI * theta.sin() * (PI / steps as f64)
+            })
+            .sum()
+    }
+
+    #[test]
+    fn phase_functions_integrate_to_one() {
+        assert!((integrate_over_the_sphere(PhaseFunction::Isotropic) - 1.).abs() < 0.001);
+        assert!((integrate_over_the_sphere(PhaseFunction::HenyeyGreenstein(0.6)) - 1.).abs() < 0.001);
+    }
+
+    #[test]
+    fn henyey_greenstein_favours_forward_scattering() {
+        let phase = PhaseFunction::HenyeyGreenstein(0.6);
+
+        assert!(phase.evaluate(1.) > phase.evaluate(-1.));
+        assert_equivalent!(PhaseFunction::HenyeyGreenstein(0.).evaluate(0.3), PhaseFunction::Isotropic.evaluate(0.3));
+    }
+
+    #[test]
+    fn transmittance_falls_off_with_the_extinction() {
+        let medium = Medium::new(Color::new(0.1, 0.2, 0.3), Color::new(0.1, 0., 0.));
+
+        assert_equivalent!(medium.transmittance(0.), Color::white());
+        assert_equivalent!(medium.transmittance(2.), Color::new((-0.4f64).exp(), (-0.4f64).exp(), (-0.6f64).exp()));
+    }
+}
@@ -0,0 +1,197 @@
+use std::f64::consts::PI;
+use crate::color::Color;
+use crate::sampling::from_local;
+use crate::tuple::Tuple;
+
+const MIN_ALPHA: f64 = 0.001;
+const DIELECTRIC_F0: f64 = 0.04;
+
+pub fn alpha(roughness: f64) -> f64 {
+    (roughness * roughness).max(MIN_ALPHA)
+}
+
+pub fn ggx_distribution(normal: Tuple, half_vector: Tuple, roughness: f64) -> f64 {
+    let alpha2 = alpha(roughness).powi(2);
+    let n_dot_h = normal.dot(half_vector).max(0.);
+    let denominator = n_dot_h * n_dot_h * (alpha2 - 1.) + 1.;
+    alpha2 / (PI * denominator * denominator)
+}
+
+pub fn smith_g1(normal: Tuple, vector: Tuple, roughness: f64) -> f64 {
+    let alpha2 = alpha(roughness).powi(2);
+    let n_dot_v = normal.dot(vector).max(0.);
+    2. * n_dot_v / (n_dot_v + (alpha2 + (1. - alpha2) * n_dot_v * n_dot_v).sqrt())
+}
+
+pub fn smith_geometry(normal: Tuple, light_vector: Tuple, eye_vector: Tuple, roughness: f64) -> f64 {
+    smith_g1(normal, light_vector, roughness) * smith_g1(normal, eye_vector, roughness)
+}
+
+pub fn fresnel_schlick(cos: f64, f0: Color) -> Color {
+    let factor = (1. - cos.clamp(0., 1.)).powi(5);
+    f0 + (Color::white() - f0) * factor
+}
+
+pub fn base_reflectance(base_color: Color, metallic: f64) -> Color {
+    let dielectric = Color::new(DIELECTRIC_F0, DIELECTRIC_F0, DIELECTRIC_F0);
+    dielectric + (base_color - dielectric) * metallic
+}
+
+// u1 and u2 are uniform in [0, 1), the result is a microfacet normal distributed as D(h) cos(theta_h)
+pub fn sample_ggx_half_vector(normal: Tuple, roughness: f64, u1: f64, u2: f64) -> Tuple {
+    let theta = (alpha(roughness) * (u1 / (1. - u1)).sqrt()).atan();
+    let phi = 2. * PI * u2;
+    from_local(normal, theta.sin() * phi.cos(), theta.sin() * phi.sin(), theta.cos())
+}
+
+// f cos / pdf of a direction reflected or refracted about a half vector from sample_ggx_half_vector, fresnel left out
+pub fn ggx_sample_weight(normal: Tuple, half_vector: Tuple, eye_vector: Tuple, out_vector: Tuple, roughness: f64) -> f64 {
+    let n_dot_v = normal.dot(eye_vector).abs();
+    let n_dot_h = normal.dot(half_vector).abs();
+    if n_dot_v <= 0. || n_dot_h <= 0. {
+        return 0.;
+    }
+    // a refracted direction is below the surface, its masking is measured from the other side
+    let out_vector = out_vector * normal.dot(out_vector).signum();
+    smith_geometry(normal, out_vector, eye_vector * normal.dot(eye_vector).signum(), roughness) * eye_vector.dot(half_vector).abs() / (n_dot_v * n_dot_h)
+}
+
+// Cook-Torrance specular plus a lambertian diffuse weighted by what the fresnel term doesn't reflect
+pub fn cook_torrance(base_color: Color, metallic: f64, roughness: f64, light_vector: Tuple, eye_vector: Tuple, normal: Tuple) -> Color {
+    let n_dot_l = normal.dot(light_vector);
+    let n_dot_v = normal.dot(eye_vector);
+    if n_dot_l <= 0. || n_dot_v <= 0. {
+        return Color::black();
+    }
+
+    let half_vector = (light_vector + eye_vector).normalize();
+    let fresnel = fresnel_schlick(half_vector.dot(eye_vector), base_reflectance(base_color, metallic));
+    let distribution = ggx_distribution(normal, half_vector, roughness);
+    let geometry = smith_geometry(normal, light_vector, eye_vector, roughness);
+
+    let specular = fresnel * (distribution * geometry / (4. * n_dot_l * n_dot_v));
+    let diffuse = (Color::white() - fresnel) * base_color * ((1. - metallic) / PI);
+    diffuse + specular
+}
+
+#[cfg(test)]
+mod tests_microfacet {
+    use crate::assert_equivalent;
+    use crate::equivalent::Equivalence;
+    use super::*;
+
+    #[test]
+    fn the_ggx_distribution_integrates_to_one_over_the_hemisphere() {
+        let normal = Tuple::vector(0., 1., 0.);
+        let steps = 2000;
+        let mut total = 0.;
+        for i in 0..steps {
+            let theta = (i as f64 + 0.5) / steps as f64 * PI / 2.;
+            let half_vector = Tuple::vector(theta.sin(), theta.cos(), 0.);
+            total += ggx_distribution(normal, half_vector, 0.5) * theta.cos() * theta.sin() * 2. * PI * (PI / 2. / steps as f64);
+        }
+
+        assert!((total - 1.).abs() < 0.001);
+    }
+
+    #[test]
+    fn smith_geometry_is_one_when_looking_straight_down_the_normal() {
+        let normal = Tuple::vector(0., 1., 0.);
+
+        assert_equivalent!(smith_geometry(normal, normal, normal, 0.7), 1.);
+        assert!(smith_g1(normal, Tuple::vector(1., 0.1, 0.).normalize(), 0.7) < 0.5);
+    }
+
+    #[test]
+    fn schlick_fresnel_goes_from_f0_to_white() {
+        let f0 = Color::new(0.04, 0.04, 0.04);
+
+        assert_equivalent!(fresnel_schlick(1., f0), f0);
+        assert_equivalent!(fresnel_schlick(0., f0), Color::white());
+    }
+
+    #[test]
+    fn metals_take_their_reflectance_from_the_base_color() {
+        let gold = Color::new(1., 0.8, 0.3);
+
+        assert_equivalent!(base_reflectance(gold, 0.), Color::new(0.04, 0.04, 0.04));
+        assert_equivalent!(base_reflectance(gold, 1.), gold);
+    }
+
+    #[test]
+    fn a_metal_has_no_diffuse_term() {
+        let normal = Tuple::vector(0., 1., 0.);
+        let light_v = Tuple::vector(1., 1., 0.).normalize();
+        let eye_v = Tuple::vector(-1., 2., 0.).normalize();
+
+        let metal = cook_torrance(Color::white(), 1., 0.1, light_v, eye_v, normal);
+        let plastic = cook_torrance(Color::white(), 0., 0.1, light_v, eye_v, normal);
+
+        assert!(metal.red < 0.05);
+        assert!(plastic.red > 0.3);
+    }
+
+    #[test]
+    fn nothing_is_reflected_below_the_horizon() {
+        let normal = Tuple::vector(0., 1., 0.);
+
+        let below = cook_torrance(Color::white(), 0., 0.5, Tuple::vector(0., -1., 0.), normal, normal);
+
+        assert_equivalent!(below, Color::black());
+    }
+
+    #[test]
+    fn a_white_furnace_reflects_about_what_it_receives() {
+        let normal = Tuple::vector(0., 1., 0.);
+        let eye_v = Tuple::vector(1., 1., 0.).normalize();
+        let steps = 200;
+        let mut total = Color::black();
+        for i in 0..steps {
+            for j in 0..steps {
+                let theta = (i as f64 + 0.5) / steps as f64 * PI / 2.;
+                let phi = (j as f64 + 0.5) / steps as f64 * 2. * PI;
+                let light_v = Tuple::vector(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin());
+                let solid_angle = theta.sin() * (PI / 2. / steps as f64) * (2. * PI / steps as f64);
+                total = total + cook_torrance(Color::white(), 0., 0.3, light_v, eye_v, normal) * (theta.cos() * solid_angle);
+            }
+        }
+
+        assert!(total.red < 1.01);
+        assert!(total.red > 0.8);
+    }
+
+    #[test]
+    fn sample_weights_average_to_the_directional_albedo() {
+        let normal = Tuple::vector(0., 1., 0.);
+        let eye_v = Tuple::vector(1., 1., 0.).normalize();
+        let straight = ggx_sample_weight(normal, normal, normal, normal, 0.5);
+        let steps = 200;
+        let mut total = 0.;
+        for i in 0..steps {
+            for j in 0..steps {
+                let half_vector = sample_ggx_half_vector(normal, 0.3, (i as f64 + 0.5) / steps as f64, (j as f64 + 0.5) / steps as f64);
+                let light_v = (-eye_v).reflect(half_vector);
+                if light_v.dot(normal) > 0. {
+                    total += ggx_sample_weight(normal, half_vector, eye_v, light_v, 0.3);
+                }
+            }
+        }
+        total /= (steps * steps) as f64;
+
+        assert_equivalent!(straight, 1.);
+        assert!(total < 1.01);
+        assert!(total > 0.8);
+    }
+
+    #[test]
+    fn sampled_half_vectors_spread_with_roughness() {
+        let normal = Tuple::vector(0., 1., 0.);
+
+        assert_equivalent!(sample_ggx_half_vector(normal, 0.5, 0., 0.3), normal);
+        let smooth = sample_ggx_half_vector(normal, 0.1, 0.5, 0.3);
+        let rough = sample_ggx_half_vector(normal, 0.9, 0.5, 0.3);
+        assert_equivalent!(rough.length(), 1.);
+        assert!(smooth.dot(normal) > rough.dot(normal));
+        assert!(smooth.dot(normal) > 0.99);
+    }
+}
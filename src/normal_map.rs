@@ -0,0 +1,107 @@
+use crate::color::Color;
+use crate::intersection::Computations;
+use crate::patterns::{Incuse, Patterns};
+use crate::tuple::Tuple;
+use crate::uv::{UvImage, UvPattern};
+
+const BUMP_DELTA: f64 = 0.0001;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum NormalMap {
+    Bump(Box<Patterns>),
+    Image(UvImage)
+}
+
+impl From<Patterns> for NormalMap {
+    fn from(pattern: Patterns) -> Self {
+        NormalMap::Bump(Box::new(pattern))
+    }
+}
+
+impl From<UvImage> for NormalMap {
+    fn from(image: UvImage) -> Self {
+        NormalMap::Image(image)
+    }
+}
+
+fn height(color: Color) -> f64 {
+    (color.red + color.green + color.blue) / 3.
+}
+
+impl NormalMap {
+    pub fn perturb(&self, comps: &Computations, strength: f64) -> Tuple {
+        let normal = comps.normal_v;
+        let tangent = comps.tangent_v;
+        let bitangent = comps.bitangent_v;
+        match *self {
+            NormalMap::Bump(ref pattern) => {
+                let height_at = |offset: Tuple| height(pattern.color_at_object(&comps.object, comps.point + offset));
+                let du = (height_at(tangent * BUMP_DELTA) - height_at(tangent * -BUMP_DELTA)) / (2. * BUMP_DELTA);
+                let dv = (height_at(bitangent * BUMP_DELTA) - height_at(bitangent * -BUMP_DELTA)) / (2. * BUMP_DELTA);
+                (normal - (tangent * du + bitangent * dv) * strength).normalize()
+            }
+            NormalMap::Image(ref image) => {
+                let texel = image.uv_color_at(comps.u, comps.v);
+                let x = (texel.red * 2. - 1.) * strength;
+                let y = (texel.green * 2. - 1.) * strength;
+                let z = texel.blue * 2. - 1.;
+                (tangent * x + bitangent * y + normal * z).normalize()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_normal_map {
+    use crate::assert_equivalent;
+    use crate::canvas::Canvas;
+    use crate::equivalent::Equivalence;
+    use crate::intersection::{Intersection, Intersections};
+    use crate::object::Object;
+    use crate::patterns::{LinearGradient, Stripe};
+    use crate::plane::Plane;
+    use crate::ray::Ray;
+    use std::borrow::Cow;
+    use super::*;
+
+    fn plane_hit() -> Computations<'static> {
+        let ray = Ray::new(Tuple::point(0.5, 1., 0.5), Tuple::vector(0., -1., 0.));
+        let intersection = Intersection::new(1., Object::from(Plane::default()));
+        let comps = intersection.prepare_computations(ray, &Intersections::new(vec![intersection.clone()]));
+        Computations { material: Cow::Owned(comps.material.into_owned()), ..comps }
+    }
+
+    #[test]
+    fn a_flat_bump_pattern_leaves_the_normal_alone() {
+        let map = NormalMap::from(Patterns::from(Stripe::new(Color::white(), Color::white())));
+
+        assert_equivalent!(map.perturb(&plane_hit(), 1.), Tuple::vector(0., 1., 0.));
+    }
+
+    #[test]
+    fn a_bump_gradient_tilts_the_normal_downhill() {
+        let map = NormalMap::from(Patterns::from(LinearGradient::new(Color::black(), Color::white())));
+
+        assert_equivalent!(map.perturb(&plane_hit(), 1.), Tuple::vector(-1., 1., 0.).normalize());
+    }
+
+    #[test]
+    fn the_bump_strength_scales_the_tilt() {
+        let map = NormalMap::from(Patterns::from(LinearGradient::new(Color::black(), Color::white())));
+        let comps = plane_hit();
+
+        assert_equivalent!(map.perturb(&comps, 0.), Tuple::vector(0., 1., 0.));
+        assert_equivalent!(map.perturb(&comps, 0.5), Tuple::vector(-0.5, 1., 0.).normalize());
+    }
+
+    #[test]
+    fn a_normal_map_texel_is_read_in_tangent_space() {
+        let mut canvas = Canvas::new(1, 1);
+        canvas.set_pixel_color(0, 0, Color::new(1., 0.5, 1.));
+        let map = NormalMap::from(UvImage::new(canvas));
+        let comps = plane_hit();
+
+        assert_equivalent!(map.perturb(&comps, 1.), Tuple::vector(1., 1., 0.).normalize());
+        assert_equivalent!(map.perturb(&comps, 0.), Tuple::vector(0., 1., 0.));
+    }
+}
@@ -9,30 +9,102 @@ use crate::cube::Cube;
 use crate::cylinder::Cylinder;
 use crate::triangle::Triangle;
 use crate::tuple::Tuple;
+use crate::EPSILON;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RayKind {
+    Camera,
+    Shadow,
+    Reflection,
+    Refraction
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Visibility {
+    pub camera: bool,
+    pub shadow: bool,
+    pub reflection: bool,
+    pub refraction: bool
+}
+
+impl Visibility {
+    pub fn allows(&self, kind: RayKind) -> bool {
+        match kind {
+            RayKind::Camera => self.camera,
+            RayKind::Shadow => self.shadow,
+            RayKind::Reflection => self.reflection,
+            RayKind::Refraction => self.refraction
+        }
+    }
+}
+
+impl Default for Visibility {
+    fn default() -> Self {
+        Visibility { camera: true, shadow: true, reflection: true, refraction: true }
+    }
+}
 
 pub trait Intersectable {
     fn local_intersect(&self, local_ray: Ray) -> Intersections;
     fn local_normal_at(&self, world_point: Tuple) -> Tuple;
+    fn local_uv_at(&self, local_point: Tuple) -> (f64, f64);
+    // direction of increasing u on the surface, it does not need to be normalized
+    fn local_tangent_at(&self, local_point: Tuple) -> Tuple;
     fn material(&self) -> Material;
+    // borrows instead of cloning, for the hot paths that only read a field or two
+    fn material_ref(&self) -> &Material;
     fn transform(&self) -> Matrix<4>;
     fn set_material(&mut self, material: Material);
     fn set_transform(&mut self, transform: Matrix<4>);
+    fn visibility(&self) -> Visibility;
+    fn set_visibility(&mut self, visibility: Visibility);
+    fn name(&self) -> Option<&str>;
+    fn set_name(&mut self, name: &str);
 
     fn intersect(&self, original_ray: Ray) -> Intersections {
         let local_ray = original_ray.set_transform(self.transform().inverse());
-        self.local_intersect(local_ray)
+        let mut xs = self.local_intersect(local_ray);
+        if let Some(cutout) = &self.material_ref().cutout {
+            xs.data.retain(|hit| cutout.is_opaque_at(&hit.object, original_ray.position(hit.t)));
+        }
+        xs
     }
 
     fn normal_at(&self, point: Tuple) -> Tuple {
-        let local_point = self.transform().inverse() * point;
+        let inverse = self.transform().inverse();
+        self.normal_from_local(&inverse, inverse * point)
+    }
+
+    // for callers that already hold the inverse transform and the local point
+    fn normal_from_local(&self, inverse: &Matrix<4>, local_point: Tuple) -> Tuple {
         let local_normal = self.local_normal_at(local_point);
-        let mut world_normal = self.transform().inverse().transpose() * local_normal;
+        let mut world_normal = inverse.transpose() * local_normal;
         world_normal.w = 0.;
         world_normal.normalize()
     }
+
+    fn uv_at(&self, point: Tuple) -> (f64, f64) {
+        let local_point = self.transform().inverse() * point;
+        self.local_uv_at(local_point)
+    }
+
+    fn tangent_at(&self, point: Tuple, normal: Tuple) -> Tuple {
+        let local_point = self.transform().inverse() * point;
+        self.tangent_from_local(local_point, normal)
+    }
+
+    fn tangent_from_local(&self, local_point: Tuple, normal: Tuple) -> Tuple {
+        let mut world_tangent = self.transform() * self.local_tangent_at(local_point);
+        world_tangent.w = 0.;
+        let tangent = world_tangent - normal * world_tangent.dot(normal);
+        if tangent.length() < EPSILON {
+            return normal.perpendicular();
+        }
+        tangent.normalize()
+    }
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Object {
     Sphere(Sphere),
     Plane(Plane),
@@ -101,14 +173,47 @@ impl Intersectable for Object {
         }
     }
 
+    fn local_uv_at(&self, point: Tuple) -> (f64, f64) {
+        match *self {
+            Object::Sphere(ref sphere) => sphere.local_uv_at(point),
+            Object::Plane(ref plane) => plane.local_uv_at(point),
+            Object::Cube(ref cube) => cube.local_uv_at(point),
+            Object::Cylinder(ref cylinder) => cylinder.local_uv_at(point),
+            Object::Cone(ref cone) => cone.local_uv_at(point),
+            Object::Triangle(ref triangle) => triangle.local_uv_at(point),
+        }
+    }
+
+    fn local_tangent_at(&self, point: Tuple) -> Tuple {
+        match *self {
+            Object::Sphere(ref sphere) => sphere.local_tangent_at(point),
+            Object::Plane(ref plane) => plane.local_tangent_at(point),
+            Object::Cube(ref cube) => cube.local_tangent_at(point),
+            Object::Cylinder(ref cylinder) => cylinder.local_tangent_at(point),
+            Object::Cone(ref cone) => cone.local_tangent_at(point),
+            Object::Triangle(ref triangle) => triangle.local_tangent_at(point),
+        }
+    }
+
     fn material(&self) -> Material {
         match *self {
-            Object::Sphere(ref sphere) => sphere.material,
-            Object::Plane(ref plane) => plane.material,
-            Object::Cube(ref cube) => cube.material,
-            Object::Cylinder(ref cylinder) => cylinder.material,
-            Object::Cone(ref cone) => cone.material,
-            Object::Triangle(ref triangle) => triangle.material,
+            Object::Sphere(ref sphere) => sphere.material.clone(),
+            Object::Plane(ref plane) => plane.material.clone(),
+            Object::Cube(ref cube) => cube.material.clone(),
+            Object::Cylinder(ref cylinder) => cylinder.material.clone(),
+            Object::Cone(ref cone) => cone.material.clone(),
+            Object::Triangle(ref triangle) => triangle.material.clone(),
+        }
+    }
+
+    fn material_ref(&self) -> &Material {
+        match *self {
+            Object::Sphere(ref sphere) => &sphere.material,
+            Object::Plane(ref plane) => &plane.material,
+            Object::Cube(ref cube) => &cube.material,
+            Object::Cylinder(ref cylinder) => &cylinder.material,
+            Object::Cone(ref cone) => &cone.material,
+            Object::Triangle(ref triangle) => &triangle.material,
         }
     }
 
@@ -144,23 +249,79 @@ impl Intersectable for Object {
             Object::Triangle(ref mut triangle) => triangle.transform = transform,
         }
     }
+
+    fn visibility(&self) -> Visibility {
+        match *self {
+            Object::Sphere(ref sphere) => sphere.visibility,
+            Object::Plane(ref plane) => plane.visibility,
+            Object::Cube(ref cube) => cube.visibility,
+            Object::Cylinder(ref cylinder) => cylinder.visibility,
+            Object::Cone(ref cone) => cone.visibility,
+            Object::Triangle(ref triangle) => triangle.visibility,
+        }
+    }
+
+    fn set_visibility(&mut self, visibility: Visibility) {
+        match *self {
+            Object::Sphere(ref mut sphere) => sphere.visibility = visibility,
+            Object::Plane(ref mut plane) => plane.visibility = visibility,
+            Object::Cube(ref mut cube) => cube.visibility = visibility,
+            Object::Cylinder(ref mut cylinder) => cylinder.visibility = visibility,
+            Object::Cone(ref mut cone) => cone.visibility = visibility,
+            Object::Triangle(ref mut triangle) => triangle.visibility = visibility,
+        }
+    }
+
+    fn name(&self) -> Option<&str> {
+        match *self {
+            Object::Sphere(ref sphere) => sphere.name(),
+            Object::Plane(ref plane) => plane.name(),
+            Object::Cube(ref cube) => cube.name(),
+            Object::Cylinder(ref cylinder) => cylinder.name(),
+            Object::Cone(ref cone) => cone.name(),
+            Object::Triangle(ref triangle) => triangle.name(),
+        }
+    }
+
+    fn set_name(&mut self, name: &str) {
+        match *self {
+            Object::Sphere(ref mut sphere) => sphere.set_name(name),
+            Object::Plane(ref mut plane) => plane.set_name(name),
+            Object::Cube(ref mut cube) => cube.set_name(name),
+            Object::Cylinder(ref mut cylinder) => cylinder.set_name(name),
+            Object::Cone(ref mut cone) => cone.set_name(name),
+            Object::Triangle(ref mut triangle) => triangle.set_name(name),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests_object {
     use crate::intersection::Intersection;
-    use crate::object::Object;
+    use crate::object::{Intersectable, Object, RayKind, Visibility};
     use crate::sphere::Sphere;
     use crate::tuple::Tuple;
 
     #[test]
     pub fn an_intersection_encapsulate_t_and_object() {
         let sphere = Sphere::new(Tuple::point(0., 0., 0.), 1.);
-        let object = Object::from(sphere);
+        let object = Object::from(sphere.clone());
 
         let intersect = Intersection::new(3.5, object);
 
         assert_eq!(intersect.t, 3.5);
         assert_eq!(intersect.object, Object::from(sphere));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn objects_are_visible_to_every_ray_by_default() {
+        let mut object = Object::from(Sphere::default());
+        assert_eq!(object.visibility(), Visibility::default());
+
+        object.set_visibility(Visibility { shadow: false, ..Visibility::default() });
+
+        assert!(object.visibility().allows(RayKind::Camera));
+        assert!(object.visibility().allows(RayKind::Reflection));
+        assert!(!object.visibility().allows(RayKind::Shadow));
+    }
+}
@@ -0,0 +1,58 @@
+use std::f64::consts::PI;
+use crate::sampling::{from_local, Rng};
+use crate::tuple::Tuple;
+
+// surroundings within max_distance darken the ambient term, 1 is fully open and 0 fully enclosed
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct AmbientOcclusion {
+    pub samples: usize,
+    pub max_distance: f64
+}
+
+impl AmbientOcclusion {
+    pub fn new(samples: usize, max_distance: f64) -> Self {
+        if samples == 0 {
+            panic!("Invalid args. Ambient occlusion needs at least one sample")
+        }
+        AmbientOcclusion { samples, max_distance }
+    }
+
+    // cosine weighted over the hemisphere around the normal
+    pub fn sample_direction(&self, normal: Tuple, rng: &mut Rng) -> Tuple {
+        let radius = rng.next_f64().sqrt();
+        let phi = 2. * PI * rng.next_f64();
+        from_local(normal, radius * phi.cos(), radius * phi.sin(), (1. - radius * radius).sqrt())
+    }
+}
+
+impl Default for AmbientOcclusion {
+    fn default() -> Self {
+        AmbientOcclusion::new(16, 1.)
+    }
+}
+
+#[cfg(test)]
+mod tests_occlusion {
+    use crate::assert_equivalent;
+    use crate::equivalent::Equivalence;
+    use super::*;
+
+    #[test]
+    fn samples_stay_in_the_hemisphere_and_favour_the_normal() {
+        let occlusion = AmbientOcclusion::default();
+        let normal = Tuple::vector(0., 0., 1.);
+        let mut rng = Rng::new(9);
+        let count = 10000;
+
+        let mut mean_cos = 0.;
+        for _ in 0..count {
+            let direction = occlusion.sample_direction(normal, &mut rng);
+            assert!(direction.dot(normal) > 0.);
+            assert_equivalent!(direction.length(), 1.);
+            mean_cos += direction.dot(normal) / count as f64;
+        }
+
+        // cosine weighting gives E[cos] = 2/3
+        assert!((mean_cos - 2. / 3.).abs() < 0.01);
+    }
+}
@@ -0,0 +1,165 @@
+use std::f64::consts::PI;
+use crate::canvas::Canvas;
+use crate::color::Color;
+use crate::tuple::Tuple;
+
+// what the camera ray first hits through a pixel, normal faces the eye
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Surface {
+    pub depth: f64,
+    pub normal: Tuple,
+    pub object_id: usize
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct GeometryBuffer {
+    pub width: usize,
+    pub height: usize,
+    surfaces: Vec<Option<Surface>>
+}
+
+impl GeometryBuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        GeometryBuffer { width, height, surfaces: vec![None; width * height] }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<Surface> {
+        self.surfaces[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, surface: Option<Surface>) {
+        self.surfaces[y * self.width + x] = surface;
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Outline {
+    pub color: Color,
+    // in pixels
+    pub width: usize,
+    // relative to the nearer of the two depths
+    pub depth_threshold: f64,
+    // radians between neighbouring normals
+    pub crease_angle: f64
+}
+
+impl Outline {
+    pub fn new(color: Color, width: usize) -> Self {
+        if width == 0 {
+            panic!("Invalid args. Outline width must be at least one pixel")
+        }
+        Outline { color, width, depth_threshold: 0.1, crease_angle: PI / 4. }
+    }
+
+    pub fn with_depth_threshold(mut self, depth_threshold: f64) -> Self {
+        self.depth_threshold = depth_threshold;
+        self
+    }
+
+    pub fn with_crease_angle(mut self, crease_angle: f64) -> Self {
+        self.crease_angle = crease_angle;
+        self
+    }
+
+    fn discontinuous(&self, a: Option<Surface>, b: Option<Surface>) -> bool {
+        match (a, b) {
+            (None, None) => false,
+            (Some(a), Some(b)) => {
+                a.object_id != b.object_id
+                    || (a.depth - b.depth).abs() > self.depth_threshold * a.depth.min(b.depth)
+                    || a.normal.dot(b.normal) < self.crease_angle.cos()
+            }
+            _ => true
+        }
+    }
+
+    // a pixel is on an edge when it differs from its right or lower neighbour
+    pub fn is_edge(&self, buffer: &GeometryBuffer, x: usize, y: usize) -> bool {
+        let surface = buffer.get(x, y);
+        (x + 1 < buffer.width && self.discontinuous(surface, buffer.get(x + 1, y)))
+            || (y + 1 < buffer.height && self.discontinuous(surface, buffer.get(x, y + 1)))
+    }
+
+    pub fn apply(&self, canvas: &mut Canvas, buffer: &GeometryBuffer) {
+        if canvas.width != buffer.width || canvas.height != buffer.height {
+            panic!("Invalid args. The canvas and geometry buffer must be the same size")
+        }
+        let radius = self.width as f64 / 2.;
+        let reach = self.width / 2;
+        for y in 0..buffer.height {
+            for x in 0..buffer.width {
+                if !self.is_edge(buffer, x, y) {
+                    continue;
+                }
+                for py in y.saturating_sub(reach)..(y + reach + 1).min(buffer.height) {
+                    for px in x.saturating_sub(reach)..(x + reach + 1).min(buffer.width) {
+                        let dx = px as f64 - x as f64;
+                        let dy = py as f64 - y as f64;
+                        if dx * dx + dy * dy <= radius * radius {
+                            canvas.set_pixel_color(px, py, self.color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_outline {
+    use crate::assert_equivalent;
+    use crate::equivalent::Equivalence;
+    use super::*;
+
+    fn flat(depth: f64, object_id: usize) -> Option<Surface> {
+        Some(Surface { depth, normal: Tuple::vector(0., 0., -1.), object_id })
+    }
+
+    fn buffer_with_column(surface: Option<Surface>) -> GeometryBuffer {
+        let mut buffer = GeometryBuffer::new(5, 5);
+        for y in 0..5 {
+            for x in 0..5 {
+                buffer.set(x, y, if x == 2 { surface } else { flat(5., 0) });
+            }
+        }
+        buffer
+    }
+
+    #[test]
+    fn a_continuous_surface_has_no_edges() {
+        let buffer = buffer_with_column(flat(5.2, 0));
+        let outline = Outline::new(Color::black(), 1);
+
+        assert!((0..5).all(|x| !outline.is_edge(&buffer, x, 2)));
+    }
+
+    #[test]
+    fn edges_come_from_depth_normal_and_object_changes() {
+        let outline = Outline::new(Color::black(), 1);
+        let tilted = Some(Surface { depth: 5., normal: Tuple::vector(1., 0., -1.).normalize(), object_id: 0 });
+
+        assert!(outline.is_edge(&buffer_with_column(flat(8., 0)), 1, 2));
+        assert!(outline.is_edge(&buffer_with_column(flat(5., 1)), 1, 2));
+        assert!(outline.is_edge(&buffer_with_column(tilted), 1, 2));
+        assert!(outline.is_edge(&buffer_with_column(None), 1, 2));
+        assert!(!outline.with_crease_angle(PI / 3.).is_edge(&buffer_with_column(tilted), 1, 2));
+    }
+
+    #[test]
+    fn outlines_are_drawn_with_their_width_and_colour() {
+        let buffer = buffer_with_column(flat(5., 1));
+        let red = Color::new(1., 0., 0.);
+
+        let mut thin = Canvas::new(5, 5);
+        Outline::new(red, 1).apply(&mut thin, &buffer);
+        let mut thick = Canvas::new(5, 5);
+        Outline::new(red, 3).apply(&mut thick, &buffer);
+
+        assert_equivalent!(thin.get_pixel_color(1, 0), red);
+        assert_equivalent!(thin.get_pixel_color(0, 0), Color::black());
+        assert_equivalent!(thin.get_pixel_color(3, 0), Color::black());
+        assert_equivalent!(thick.get_pixel_color(0, 0), red);
+        assert_equivalent!(thick.get_pixel_color(3, 0), red);
+        assert_equivalent!(thick.get_pixel_color(4, 0), Color::black());
+    }
+}
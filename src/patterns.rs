@@ -1,8 +1,11 @@
+use std::f64::consts::TAU;
 use crate::color::Color;
 use crate::equivalent::Equivalence;
 use crate::matrix::Matrix;
 use crate::object::{Intersectable, Object};
+use crate::ramp::ColorRamp;
 use crate::tuple::Tuple;
+use crate::uv::{UvMapping, UvPattern, UvPatterns};
 
 pub trait Incuse {
     fn color_a(&self) -> Color;
@@ -10,30 +13,66 @@ pub trait Incuse {
     fn transform(&self) -> Matrix<4>;
     fn set_pattern_transform(&mut self, transform: Matrix<4>);
 
-    fn color_at_object(&self, object: Object, world_point: Tuple) -> Color {
+    fn pattern_a(&self) -> Option<&Patterns> {
+        None
+    }
+
+    fn pattern_b(&self) -> Option<&Patterns> {
+        None
+    }
+
+    fn color_at_object(&self, object: &Object, world_point: Tuple) -> Color {
         let obj_point = object.transform().inverse() * world_point;
-        let pattern_point = self.transform().inverse() * obj_point;
+        self.color_at_parent(obj_point)
+    }
+
+    // `point` is in the space of whatever contains this pattern: the object for a
+    // top level pattern, or the parent pattern when nested.
+    fn color_at_parent(&self, point: Tuple) -> Color {
+        let pattern_point = self.transform().inverse() * point;
         self.color_at(pattern_point)
     }
 
+    fn color_a_at(&self, point: Tuple) -> Color {
+        match self.pattern_a() {
+            Some(pattern) => pattern.color_at_parent(point),
+            None => self.color_a()
+        }
+    }
+
+    fn color_b_at(&self, point: Tuple) -> Color {
+        match self.pattern_b() {
+            Some(pattern) => pattern.color_at_parent(point),
+            None => self.color_b()
+        }
+    }
+
     fn color_at(&self, point: Tuple) -> Color {
         Color::new(point.x, point.y, point.z)
     }
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Patterns {
     Stripe(Stripe),
     LinearGradient(LinearGradient),
     Ring(Ring),
     Checkers(Checkers),
     DefaultPattern(DefaultPattern),
+    Blend(Blend),
+    Mask(Mask),
+    RadialGradient(RadialGradient),
+    SphericalGradient(SphericalGradient),
+    AngularGradient(AngularGradient),
+    TextureMap(TextureMap),
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Stripe {
     color_a: Color,
     color_b: Color,
+    pattern_a: Option<Box<Patterns>>,
+    pattern_b: Option<Box<Patterns>>,
     transform: Matrix<4>
 }
 
@@ -51,14 +90,26 @@ impl From<Stripe> for Patterns {
 
 impl Stripe {
     pub fn new(color_a: Color, color_b: Color) -> Self {
-        Stripe { color_a, color_b, transform: Matrix::identity() }
+        Stripe { color_a, color_b, pattern_a: None, pattern_b: None, transform: Matrix::identity() }
+    }
+
+    pub fn nested(pattern_a: Patterns, pattern_b: Patterns) -> Self {
+        Stripe {
+            color_a: pattern_a.color_a(),
+            color_b: pattern_b.color_b(),
+            pattern_a: Some(Box::new(pattern_a)),
+            pattern_b: Some(Box::new(pattern_b)),
+            transform: Matrix::identity()
+        }
     }
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct LinearGradient {
     color_a: Color,
     color_b: Color,
+    pattern_a: Option<Box<Patterns>>,
+    pattern_b: Option<Box<Patterns>>,
     transform: Matrix<4>
 }
 
@@ -76,14 +127,26 @@ impl From<LinearGradient> for Patterns {
 
 impl LinearGradient {
     pub fn new(color_a: Color, color_b: Color) -> Self {
-        LinearGradient { color_a, color_b, transform: Matrix::identity() }
+        LinearGradient { color_a, color_b, pattern_a: None, pattern_b: None, transform: Matrix::identity() }
+    }
+
+    pub fn nested(pattern_a: Patterns, pattern_b: Patterns) -> Self {
+        LinearGradient {
+            color_a: pattern_a.color_a(),
+            color_b: pattern_b.color_b(),
+            pattern_a: Some(Box::new(pattern_a)),
+            pattern_b: Some(Box::new(pattern_b)),
+            transform: Matrix::identity()
+        }
     }
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Ring {
     color_a: Color,
     color_b: Color,
+    pattern_a: Option<Box<Patterns>>,
+    pattern_b: Option<Box<Patterns>>,
     transform: Matrix<4>
 }
 
@@ -101,14 +164,26 @@ impl From<Ring> for Patterns {
 
 impl Ring {
     pub fn new(color_a: Color, color_b: Color) -> Self {
-        Ring { color_a, color_b, transform: Matrix::identity() }
+        Ring { color_a, color_b, pattern_a: None, pattern_b: None, transform: Matrix::identity() }
+    }
+
+    pub fn nested(pattern_a: Patterns, pattern_b: Patterns) -> Self {
+        Ring {
+            color_a: pattern_a.color_a(),
+            color_b: pattern_b.color_b(),
+            pattern_a: Some(Box::new(pattern_a)),
+            pattern_b: Some(Box::new(pattern_b)),
+            transform: Matrix::identity()
+        }
     }
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Checkers {
     color_a: Color,
     color_b: Color,
+    pattern_a: Option<Box<Patterns>>,
+    pattern_b: Option<Box<Patterns>>,
     transform: Matrix<4>
 }
 
@@ -126,11 +201,21 @@ impl From<Checkers> for Patterns {
 
 impl Checkers {
     pub fn new(color_a: Color, color_b: Color) -> Self {
-        Checkers { color_a, color_b, transform: Matrix::identity() }
+        Checkers { color_a, color_b, pattern_a: None, pattern_b: None, transform: Matrix::identity() }
+    }
+
+    pub fn nested(pattern_a: Patterns, pattern_b: Patterns) -> Self {
+        Checkers {
+            color_a: pattern_a.color_a(),
+            color_b: pattern_b.color_b(),
+            pattern_a: Some(Box::new(pattern_a)),
+            pattern_b: Some(Box::new(pattern_b)),
+            transform: Matrix::identity()
+        }
     }
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct DefaultPattern {
     color_a: Color,
     color_b: Color,
@@ -155,6 +240,147 @@ impl DefaultPattern {
     }
 }
 
+#[derive(Debug, PartialEq, Clone)]
+pub struct Blend {
+    pattern_a: Box<Patterns>,
+    pattern_b: Box<Patterns>,
+    pub weight: f64,
+    transform: Matrix<4>
+}
+
+impl From<Blend> for Patterns {
+    fn from(blend: Blend) -> Self {
+        Patterns::Blend(blend)
+    }
+}
+
+impl Blend {
+    pub fn new(pattern_a: Patterns, pattern_b: Patterns, weight: f64) -> Self {
+        Blend {
+            pattern_a: Box::new(pattern_a),
+            pattern_b: Box::new(pattern_b),
+            weight,
+            transform: Matrix::identity()
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Mask {
+    pattern_a: Box<Patterns>,
+    pattern_b: Box<Patterns>,
+    mask: Box<Patterns>,
+    transform: Matrix<4>
+}
+
+impl From<Mask> for Patterns {
+    fn from(mask: Mask) -> Self {
+        Patterns::Mask(mask)
+    }
+}
+
+impl Mask {
+    pub fn new(pattern_a: Patterns, pattern_b: Patterns, mask: Patterns) -> Self {
+        Mask {
+            pattern_a: Box::new(pattern_a),
+            pattern_b: Box::new(pattern_b),
+            mask: Box::new(mask),
+            transform: Matrix::identity()
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct RadialGradient {
+    pub ramp: ColorRamp,
+    transform: Matrix<4>
+}
+
+impl Default for RadialGradient {
+    fn default() -> Self {
+        RadialGradient::new(ColorRamp::linear(Color::white(), Color::black()))
+    }
+}
+
+impl From<RadialGradient> for Patterns {
+    fn from(radial_gradient: RadialGradient) -> Self {
+        Patterns::RadialGradient(radial_gradient)
+    }
+}
+
+impl RadialGradient {
+    pub fn new(ramp: ColorRamp) -> Self {
+        RadialGradient { ramp, transform: Matrix::identity() }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SphericalGradient {
+    pub ramp: ColorRamp,
+    transform: Matrix<4>
+}
+
+impl Default for SphericalGradient {
+    fn default() -> Self {
+        SphericalGradient::new(ColorRamp::linear(Color::white(), Color::black()))
+    }
+}
+
+impl From<SphericalGradient> for Patterns {
+    fn from(spherical_gradient: SphericalGradient) -> Self {
+        Patterns::SphericalGradient(spherical_gradient)
+    }
+}
+
+impl SphericalGradient {
+    pub fn new(ramp: ColorRamp) -> Self {
+        SphericalGradient { ramp, transform: Matrix::identity() }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct AngularGradient {
+    pub ramp: ColorRamp,
+    transform: Matrix<4>
+}
+
+impl Default for AngularGradient {
+    fn default() -> Self {
+        AngularGradient::new(ColorRamp::linear(Color::white(), Color::black()))
+    }
+}
+
+impl From<AngularGradient> for Patterns {
+    fn from(angular_gradient: AngularGradient) -> Self {
+        Patterns::AngularGradient(angular_gradient)
+    }
+}
+
+impl AngularGradient {
+    pub fn new(ramp: ColorRamp) -> Self {
+        AngularGradient { ramp, transform: Matrix::identity() }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct TextureMap {
+    pub uv_pattern: UvPatterns,
+    pub mapping: UvMapping,
+    transform: Matrix<4>
+}
+
+impl From<TextureMap> for Patterns {
+    fn from(texture_map: TextureMap) -> Self {
+        Patterns::TextureMap(texture_map)
+    }
+}
+
+impl TextureMap {
+    pub fn new(uv_pattern: UvPatterns, mapping: UvMapping) -> Self {
+        TextureMap { uv_pattern, mapping, transform: Matrix::identity() }
+    }
+}
+
 impl Incuse for Stripe {
     fn color_a(&self) -> Color {
         self.color_a
@@ -164,6 +390,14 @@ impl Incuse for Stripe {
         self.color_b
     }
 
+    fn pattern_a(&self) -> Option<&Patterns> {
+        self.pattern_a.as_deref()
+    }
+
+    fn pattern_b(&self) -> Option<&Patterns> {
+        self.pattern_b.as_deref()
+    }
+
     fn transform(&self) -> Matrix<4> {
         self.transform
     }
@@ -175,9 +409,9 @@ impl Incuse for Stripe {
     fn color_at(&self, point: Tuple) -> Color {
         let x = point.x;
         if (x.floor() % 2.).equivalent(0.) {
-            self.color_a
+            self.color_a_at(point)
         } else {
-            self.color_b
+            self.color_b_at(point)
         }
     }
 }
@@ -191,6 +425,14 @@ impl Incuse for LinearGradient {
         self.color_b
     }
 
+    fn pattern_a(&self) -> Option<&Patterns> {
+        self.pattern_a.as_deref()
+    }
+
+    fn pattern_b(&self) -> Option<&Patterns> {
+        self.pattern_b.as_deref()
+    }
+
     fn transform(&self) -> Matrix<4> {
         self.transform
     }
@@ -200,7 +442,9 @@ impl Incuse for LinearGradient {
     }
 
     fn color_at(&self, point: Tuple) -> Color {
-        self.color_a + ((self.color_b - self.color_a) * point.x)
+        let color_a = self.color_a_at(point);
+        let color_b = self.color_b_at(point);
+        color_a + ((color_b - color_a) * point.x)
 
         // another way
         //let distance = self.color_b - self.color_a;
@@ -218,6 +462,14 @@ impl Incuse for Ring {
         self.color_b
     }
 
+    fn pattern_a(&self) -> Option<&Patterns> {
+        self.pattern_a.as_deref()
+    }
+
+    fn pattern_b(&self) -> Option<&Patterns> {
+        self.pattern_b.as_deref()
+    }
+
     fn transform(&self) -> Matrix<4> {
         self.transform
     }
@@ -228,9 +480,9 @@ impl Incuse for Ring {
 
     fn color_at(&self, point: Tuple) -> Color {
         if ((point.x.powi(2) + point.z.powi(2)).sqrt().floor() % 2.).equivalent(0.) {
-            self.color_a
+            self.color_a_at(point)
         } else {
-            self.color_b
+            self.color_b_at(point)
         }
     }
 }
@@ -244,6 +496,14 @@ impl Incuse for Checkers {
         self.color_b
     }
 
+    fn pattern_a(&self) -> Option<&Patterns> {
+        self.pattern_a.as_deref()
+    }
+
+    fn pattern_b(&self) -> Option<&Patterns> {
+        self.pattern_b.as_deref()
+    }
+
     fn transform(&self) -> Matrix<4> {
         self.transform
     }
@@ -254,9 +514,9 @@ impl Incuse for Checkers {
 
     fn color_at(&self, point: Tuple) -> Color {
         if ((point.x.floor() + point.y.floor() + point.z.floor()) % 2.).equivalent(0.) {
-            self.color_a
+            self.color_a_at(point)
         } else {
-            self.color_b
+            self.color_b_at(point)
         }
     }
 }
@@ -279,6 +539,162 @@ impl Incuse for DefaultPattern {
     }
 }
 
+impl Incuse for Blend {
+    fn color_a(&self) -> Color {
+        self.pattern_a.color_a()
+    }
+
+    fn color_b(&self) -> Color {
+        self.pattern_b.color_b()
+    }
+
+    fn transform(&self) -> Matrix<4> {
+        self.transform
+    }
+
+    fn set_pattern_transform(&mut self, transform: Matrix<4>) {
+        self.transform = transform
+    }
+
+    fn pattern_a(&self) -> Option<&Patterns> {
+        Some(&self.pattern_a)
+    }
+
+    fn pattern_b(&self) -> Option<&Patterns> {
+        Some(&self.pattern_b)
+    }
+
+    fn color_at(&self, point: Tuple) -> Color {
+        self.color_a_at(point) * (1. - self.weight) + self.color_b_at(point) * self.weight
+    }
+}
+
+impl Incuse for Mask {
+    fn color_a(&self) -> Color {
+        self.pattern_a.color_a()
+    }
+
+    fn color_b(&self) -> Color {
+        self.pattern_b.color_b()
+    }
+
+    fn transform(&self) -> Matrix<4> {
+        self.transform
+    }
+
+    fn set_pattern_transform(&mut self, transform: Matrix<4>) {
+        self.transform = transform
+    }
+
+    fn pattern_a(&self) -> Option<&Patterns> {
+        Some(&self.pattern_a)
+    }
+
+    fn pattern_b(&self) -> Option<&Patterns> {
+        Some(&self.pattern_b)
+    }
+
+    // the mask is read as grayscale: black selects pattern_a, white selects pattern_b
+    fn color_at(&self, point: Tuple) -> Color {
+        let mask = self.mask.color_at_parent(point);
+        let weight = ((mask.red + mask.green + mask.blue) / 3.).clamp(0., 1.);
+        self.color_a_at(point) * (1. - weight) + self.color_b_at(point) * weight
+    }
+}
+
+impl Incuse for RadialGradient {
+    fn color_a(&self) -> Color {
+        self.ramp.first()
+    }
+
+    fn color_b(&self) -> Color {
+        self.ramp.last()
+    }
+
+    fn transform(&self) -> Matrix<4> {
+        self.transform
+    }
+
+    fn set_pattern_transform(&mut self, transform: Matrix<4>) {
+        self.transform = transform
+    }
+
+    // ramp position is the distance from the y axis
+    fn color_at(&self, point: Tuple) -> Color {
+        self.ramp.color_at((point.x.powi(2) + point.z.powi(2)).sqrt())
+    }
+}
+
+impl Incuse for SphericalGradient {
+    fn color_a(&self) -> Color {
+        self.ramp.first()
+    }
+
+    fn color_b(&self) -> Color {
+        self.ramp.last()
+    }
+
+    fn transform(&self) -> Matrix<4> {
+        self.transform
+    }
+
+    fn set_pattern_transform(&mut self, transform: Matrix<4>) {
+        self.transform = transform
+    }
+
+    // ramp position is the distance from the pattern origin
+    fn color_at(&self, point: Tuple) -> Color {
+        self.ramp.color_at((point.x.powi(2) + point.y.powi(2) + point.z.powi(2)).sqrt())
+    }
+}
+
+impl Incuse for AngularGradient {
+    fn color_a(&self) -> Color {
+        self.ramp.first()
+    }
+
+    fn color_b(&self) -> Color {
+        self.ramp.last()
+    }
+
+    fn transform(&self) -> Matrix<4> {
+        self.transform
+    }
+
+    fn set_pattern_transform(&mut self, transform: Matrix<4>) {
+        self.transform = transform
+    }
+
+    // ramp position is the angle around the y axis, starting at +x, as a fraction of a turn
+    fn color_at(&self, point: Tuple) -> Color {
+        let turn = (point.z.atan2(point.x) / TAU).rem_euclid(1.);
+        self.ramp.color_at(turn)
+    }
+}
+
+impl Incuse for TextureMap {
+    fn color_a(&self) -> Color {
+        self.uv_pattern.uv_color_at(0., 0.)
+    }
+
+    fn color_b(&self) -> Color {
+        self.uv_pattern.uv_color_at(1., 1.)
+    }
+
+    fn transform(&self) -> Matrix<4> {
+        self.transform
+    }
+
+    fn set_pattern_transform(&mut self, transform: Matrix<4>) {
+        self.transform = transform
+    }
+
+    fn color_at(&self, point: Tuple) -> Color {
+        let (u, v) = self.mapping.uv_at(point);
+        self.uv_pattern.uv_color_at(u, v)
+    }
+}
+
 impl Incuse for Patterns {
     fn color_a(&self) -> Color {
         match *self {
@@ -287,6 +703,12 @@ impl Incuse for Patterns {
             Patterns::Ring(ref ring) => ring.color_a,
             Patterns::Checkers(ref checkers) => checkers.color_a,
             Patterns::DefaultPattern(ref default_pattern) => default_pattern.color_a,
+            Patterns::Blend(ref blend) => blend.color_a(),
+            Patterns::Mask(ref mask) => mask.color_a(),
+            Patterns::RadialGradient(ref radial_gradient) => radial_gradient.color_a(),
+            Patterns::SphericalGradient(ref spherical_gradient) => spherical_gradient.color_a(),
+            Patterns::AngularGradient(ref angular_gradient) => angular_gradient.color_a(),
+            Patterns::TextureMap(ref texture_map) => texture_map.color_a(),
         }
     }
 
@@ -297,6 +719,12 @@ impl Incuse for Patterns {
             Patterns::Ring(ref ring) => ring.color_b,
             Patterns::Checkers(ref checkers) => checkers.color_b,
             Patterns::DefaultPattern(ref default_pattern) => default_pattern.color_b,
+            Patterns::Blend(ref blend) => blend.color_b(),
+            Patterns::Mask(ref mask) => mask.color_b(),
+            Patterns::RadialGradient(ref radial_gradient) => radial_gradient.color_b(),
+            Patterns::SphericalGradient(ref spherical_gradient) => spherical_gradient.color_b(),
+            Patterns::AngularGradient(ref angular_gradient) => angular_gradient.color_b(),
+            Patterns::TextureMap(ref texture_map) => texture_map.color_b(),
         }
     }
 
@@ -307,6 +735,44 @@ impl Incuse for Patterns {
             Patterns::Ring(ref ring) => ring.transform,
             Patterns::Checkers(ref checkers) => checkers.transform,
             Patterns::DefaultPattern(ref default_pattern) => default_pattern.transform,
+            Patterns::Blend(ref blend) => blend.transform,
+            Patterns::Mask(ref mask) => mask.transform,
+            Patterns::RadialGradient(ref radial_gradient) => radial_gradient.transform,
+            Patterns::SphericalGradient(ref spherical_gradient) => spherical_gradient.transform,
+            Patterns::AngularGradient(ref angular_gradient) => angular_gradient.transform,
+            Patterns::TextureMap(ref texture_map) => texture_map.transform,
+        }
+    }
+
+    fn pattern_a(&self) -> Option<&Patterns> {
+        match *self {
+            Patterns::Stripe(ref stripe) => stripe.pattern_a(),
+            Patterns::LinearGradient(ref linear_gradient) => linear_gradient.pattern_a(),
+            Patterns::Ring(ref ring) => ring.pattern_a(),
+            Patterns::Checkers(ref checkers) => checkers.pattern_a(),
+            Patterns::DefaultPattern(ref default_pattern) => default_pattern.pattern_a(),
+            Patterns::Blend(ref blend) => blend.pattern_a(),
+            Patterns::Mask(ref mask) => mask.pattern_a(),
+            Patterns::RadialGradient(ref radial_gradient) => radial_gradient.pattern_a(),
+            Patterns::SphericalGradient(ref spherical_gradient) => spherical_gradient.pattern_a(),
+            Patterns::AngularGradient(ref angular_gradient) => angular_gradient.pattern_a(),
+            Patterns::TextureMap(ref texture_map) => texture_map.pattern_a(),
+        }
+    }
+
+    fn pattern_b(&self) -> Option<&Patterns> {
+        match *self {
+            Patterns::Stripe(ref stripe) => stripe.pattern_b(),
+            Patterns::LinearGradient(ref linear_gradient) => linear_gradient.pattern_b(),
+            Patterns::Ring(ref ring) => ring.pattern_b(),
+            Patterns::Checkers(ref checkers) => checkers.pattern_b(),
+            Patterns::DefaultPattern(ref default_pattern) => default_pattern.pattern_b(),
+            Patterns::Blend(ref blend) => blend.pattern_b(),
+            Patterns::Mask(ref mask) => mask.pattern_b(),
+            Patterns::RadialGradient(ref radial_gradient) => radial_gradient.pattern_b(),
+            Patterns::SphericalGradient(ref spherical_gradient) => spherical_gradient.pattern_b(),
+            Patterns::AngularGradient(ref angular_gradient) => angular_gradient.pattern_b(),
+            Patterns::TextureMap(ref texture_map) => texture_map.pattern_b(),
         }
     }
 
@@ -317,16 +783,28 @@ impl Incuse for Patterns {
             Patterns::Ring(ref mut ring) => ring.set_pattern_transform(transform),
             Patterns::Checkers(ref mut checkers) => checkers.set_pattern_transform(transform),
             Patterns::DefaultPattern(ref mut default_pattern) => default_pattern.set_pattern_transform(transform),
+            Patterns::Blend(ref mut blend) => blend.set_pattern_transform(transform),
+            Patterns::Mask(ref mut mask) => mask.set_pattern_transform(transform),
+            Patterns::RadialGradient(ref mut radial_gradient) => radial_gradient.set_pattern_transform(transform),
+            Patterns::SphericalGradient(ref mut spherical_gradient) => spherical_gradient.set_pattern_transform(transform),
+            Patterns::AngularGradient(ref mut angular_gradient) => angular_gradient.set_pattern_transform(transform),
+            Patterns::TextureMap(ref mut texture_map) => texture_map.set_pattern_transform(transform),
         }
     }
 
-    fn color_at_object(&self, object: Object, world_point: Tuple) -> Color {
+    fn color_at_object(&self, object: &Object, world_point: Tuple) -> Color {
         match *self {
             Patterns::Stripe(ref stripe) => stripe.color_at_object(object, world_point),
             Patterns::LinearGradient(ref linear_gradient) => linear_gradient.color_at_object(object, world_point),
             Patterns::Ring(ref ring) => ring.color_at_object(object, world_point),
             Patterns::Checkers(ref checkers) => checkers.color_at_object(object, world_point),
             Patterns::DefaultPattern(ref default_pattern) => default_pattern.color_at_object(object, world_point),
+            Patterns::Blend(ref blend) => blend.color_at_object(object, world_point),
+            Patterns::Mask(ref mask) => mask.color_at_object(object, world_point),
+            Patterns::RadialGradient(ref radial_gradient) => radial_gradient.color_at_object(object, world_point),
+            Patterns::SphericalGradient(ref spherical_gradient) => spherical_gradient.color_at_object(object, world_point),
+            Patterns::AngularGradient(ref angular_gradient) => angular_gradient.color_at_object(object, world_point),
+            Patterns::TextureMap(ref texture_map) => texture_map.color_at_object(object, world_point),
         }
     }
 
@@ -337,6 +815,12 @@ impl Incuse for Patterns {
             Patterns::Ring(ref ring) => ring.color_at(point),
             Patterns::Checkers(ref checkers) => checkers.color_at(point),
             Patterns::DefaultPattern(ref default_pattern) => default_pattern.color_at(point),
+            Patterns::Blend(ref blend) => blend.color_at(point),
+            Patterns::Mask(ref mask) => mask.color_at(point),
+            Patterns::RadialGradient(ref radial_gradient) => radial_gradient.color_at(point),
+            Patterns::SphericalGradient(ref spherical_gradient) => spherical_gradient.color_at(point),
+            Patterns::AngularGradient(ref angular_gradient) => angular_gradient.color_at(point),
+            Patterns::TextureMap(ref texture_map) => texture_map.color_at(point),
         }
     }
 }
@@ -346,7 +830,12 @@ mod tests_patterns {
     use crate::color::Color;
     use crate::matrix::Matrix;
     use crate::object::{Intersectable, Object};
-    use crate::patterns::{Checkers, LinearGradient, Incuse, Patterns, Ring, Stripe};
+    use crate::assert_equivalent;
+    use crate::equivalent::Equivalence;
+    use crate::patterns::{AngularGradient, Blend, Checkers, LinearGradient, Incuse, Mask, Patterns, RadialGradient, Ring, SphericalGradient, Stripe};
+    use crate::ramp::{ColorRamp, Interpolation};
+    use crate::uv::{UvAlignCheck, UvCheckers, UvMapping, UvPatterns};
+    use crate::patterns::TextureMap;
     use crate::sphere::Sphere;
     use crate::tuple::Tuple;
 
@@ -393,7 +882,7 @@ mod tests_patterns {
 
         let pattern = Patterns::from(Stripe::new(Color::white(), Color::black()));
 
-        let color = pattern.color_at_object(Object::from(sphere), Tuple::point(1.5, 0., 0.));
+        let color = pattern.color_at_object(&Object::from(sphere.clone()), Tuple::point(1.5, 0., 0.));
 
         assert_eq!(color, Color::white());
     }
@@ -405,7 +894,7 @@ mod tests_patterns {
         let mut pattern = Patterns::from(Stripe::new(Color::white(), Color::black()));
         pattern.set_pattern_transform(Matrix::scaling(Tuple::point(2., 2., 2.)));
 
-        let color = pattern.color_at_object(Object::from(sphere), Tuple::point(1.5, 0., 0.));
+        let color = pattern.color_at_object(&Object::from(sphere), Tuple::point(1.5, 0., 0.));
 
         assert_eq!(color, Color::white());
     }
@@ -418,7 +907,7 @@ mod tests_patterns {
         let mut pattern = Patterns::from(Stripe::new(Color::white(), Color::black()));
         pattern.set_pattern_transform(Matrix::translation(Tuple::point(0.5, 0., 0.)));
 
-        let color = pattern.color_at_object(Object::from(sphere), Tuple::point(2.5, 0., 0.));
+        let color = pattern.color_at_object(&Object::from(sphere.clone()), Tuple::point(2.5, 0., 0.));
 
         assert_eq!(color, Color::white());
     }
@@ -506,4 +995,154 @@ mod tests_patterns {
         assert_eq!(color3, Color::black());
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn checkers_of_stripes_use_the_sub_pattern_on_each_side() {
+        let red = Color::new(1., 0., 0.);
+        let blue = Color::new(0., 0., 1.);
+        let stripes = Patterns::from(Stripe::new(red, blue));
+        let pattern = Patterns::from(Checkers::nested(stripes, Patterns::from(Stripe::default())));
+
+        assert_eq!(pattern.color_at(Tuple::point(0.5, 0.5, 0.5)), red);
+        assert_eq!(pattern.color_at(Tuple::point(1.5, 0.5, 0.5)), Color::black());
+        assert_eq!(pattern.color_at(Tuple::point(2.5, 0.5, 0.5)), red);
+        assert_eq!(pattern.color_at(Tuple::point(0.5, 1.5, 0.5)), Color::white());
+    }
+
+    #[test]
+    fn a_nested_pattern_applies_its_own_transformation() {
+        let mut stripes = Patterns::from(Stripe::default());
+        stripes.set_pattern_transform(Matrix::scaling(Tuple::vector(0.25, 1., 1.)));
+        let pattern = Patterns::from(Checkers::nested(stripes, Patterns::from(Stripe::default())));
+
+        assert_eq!(pattern.color_at(Tuple::point(0.1, 0., 0.)), Color::white());
+        assert_eq!(pattern.color_at(Tuple::point(0.3, 0., 0.)), Color::black());
+        assert_eq!(pattern.color_at(Tuple::point(0.6, 0., 0.)), Color::white());
+    }
+
+    #[test]
+    fn nesting_keeps_the_sub_pattern_colors() {
+        let pattern = Stripe::nested(
+            Patterns::from(Ring::new(Color::white(), Color::black())),
+            Patterns::from(Checkers::new(Color::black(), Color::white()))
+        );
+
+        assert_eq!(pattern.color_a(), Color::white());
+        assert_eq!(pattern.color_b(), Color::white());
+    }
+
+    #[test]
+    fn a_blend_pattern_mixes_two_patterns_by_weight() {
+        let red = Color::new(1., 0., 0.);
+        let blue = Color::new(0., 0., 1.);
+        let pattern = Patterns::from(Blend::new(
+            Patterns::from(Stripe::new(red, Color::black())),
+            Patterns::from(Stripe::new(blue, Color::white())),
+            0.25
+        ));
+
+        assert_eq!(pattern.color_at(Tuple::point(0., 0., 0.)), Color::new(0.75, 0., 0.25));
+        assert_eq!(pattern.color_at(Tuple::point(1., 0., 0.)), Color::new(0.25, 0.25, 0.25));
+    }
+
+    #[test]
+    fn a_mask_pattern_selects_between_patterns() {
+        let red = Color::new(1., 0., 0.);
+        let blue = Color::new(0., 0., 1.);
+        let mask = Patterns::from(Stripe::new(Color::black(), Color::white()));
+        let pattern = Patterns::from(Mask::new(
+            Patterns::from(Checkers::new(red, red)),
+            Patterns::from(Checkers::new(blue, blue)),
+            mask
+        ));
+
+        assert_eq!(pattern.color_at(Tuple::point(0.5, 0., 0.)), red);
+        assert_eq!(pattern.color_at(Tuple::point(1.5, 0., 0.)), blue);
+    }
+
+    #[test]
+    fn a_mask_pattern_blends_with_gray_mask_values() {
+        let mask = Patterns::from(LinearGradient::new(Color::black(), Color::white()));
+        let pattern = Patterns::from(Mask::new(
+            Patterns::from(Stripe::new(Color::new(1., 0., 0.), Color::new(1., 0., 0.))),
+            Patterns::from(Stripe::new(Color::new(0., 1., 0.), Color::new(0., 1., 0.))),
+            mask
+        ));
+
+        assert_eq!(pattern.color_at(Tuple::point(0.5, 0., 0.)), Color::new(0.5, 0.5, 0.));
+    }
+
+    fn rgb_ramp() -> ColorRamp {
+        ColorRamp::new(vec![
+            (0., Color::new(1., 0., 0.)),
+            (1., Color::new(0., 1., 0.)),
+            (2., Color::new(0., 0., 1.)),
+        ], Interpolation::Linear)
+    }
+
+    #[test]
+    fn a_radial_gradient_follows_the_distance_from_the_y_axis() {
+        let pattern = Patterns::from(RadialGradient::new(rgb_ramp()));
+
+        assert_equivalent!(pattern.color_at(Tuple::point(0., 5., 0.)), Color::new(1., 0., 0.));
+        assert_equivalent!(pattern.color_at(Tuple::point(0., 0., 0.5)), Color::new(0.5, 0.5, 0.));
+        assert_equivalent!(pattern.color_at(Tuple::point(0.6, 3., 0.8)), Color::new(0., 1., 0.));
+        assert_equivalent!(pattern.color_at(Tuple::point(1.5, 0., 0.)), Color::new(0., 0.5, 0.5));
+    }
+
+    #[test]
+    fn a_spherical_gradient_follows_the_distance_from_the_origin() {
+        let pattern = Patterns::from(SphericalGradient::new(rgb_ramp()));
+
+        assert_equivalent!(pattern.color_at(Tuple::point(0., 1., 0.)), Color::new(0., 1., 0.));
+        assert_equivalent!(pattern.color_at(Tuple::point(0., 0., -2.)), Color::new(0., 0., 1.));
+        assert_equivalent!(pattern.color_at(Tuple::point(0., 3., 4.)), Color::new(0., 0., 1.));
+    }
+
+    #[test]
+    fn an_angular_gradient_sweeps_around_the_y_axis() {
+        let ramp = ColorRamp::new(vec![(0., Color::black()), (1., Color::white())], Interpolation::Linear);
+        let pattern = Patterns::from(AngularGradient::new(ramp));
+
+        assert_equivalent!(pattern.color_at(Tuple::point(1., 0., 0.)), Color::black());
+        assert_equivalent!(pattern.color_at(Tuple::point(0., 0., 1.)), Color::new(0.25, 0.25, 0.25));
+        assert_equivalent!(pattern.color_at(Tuple::point(-1., 0., 0.)), Color::new(0.5, 0.5, 0.5));
+        assert_equivalent!(pattern.color_at(Tuple::point(0., 0., -1.)), Color::new(0.75, 0.75, 0.75));
+    }
+
+    #[test]
+    fn ramp_patterns_report_the_end_stops_as_their_colors() {
+        let pattern = Patterns::from(RadialGradient::new(rgb_ramp()));
+
+        assert_eq!(pattern.color_a(), Color::new(1., 0., 0.));
+        assert_eq!(pattern.color_b(), Color::new(0., 0., 1.));
+    }
+
+    #[test]
+    fn using_a_texture_map_pattern_with_a_spherical_map() {
+        let checkers = UvCheckers::new(16., 8., Color::black(), Color::white());
+        let pattern = Patterns::from(TextureMap::new(UvPatterns::from(checkers), UvMapping::Spherical));
+
+        assert_eq!(pattern.color_at(Tuple::point(0.4315, 0.4670, 0.7719)), Color::white());
+        assert_eq!(pattern.color_at(Tuple::point(-0.9654, 0.2552, -0.0534)), Color::black());
+        assert_eq!(pattern.color_at(Tuple::point(0.1039, 0.7090, 0.6975)), Color::white());
+        assert_eq!(pattern.color_at(Tuple::point(-0.4986, -0.7856, -0.3663)), Color::black());
+        assert_eq!(pattern.color_at(Tuple::point(-0.0317, -0.9395, 0.3411)), Color::black());
+        assert_eq!(pattern.color_at(Tuple::point(0.4809, -0.7721, 0.4154)), Color::black());
+        assert_eq!(pattern.color_at(Tuple::point(0.0285, -0.9612, -0.2745)), Color::black());
+        assert_eq!(pattern.color_at(Tuple::point(-0.5734, -0.2162, -0.7903)), Color::white());
+        assert_eq!(pattern.color_at(Tuple::point(0.7688, -0.1470, 0.6223)), Color::black());
+        assert_eq!(pattern.color_at(Tuple::point(-0.7652, 0.2175, 0.6060)), Color::black());
+    }
+
+    #[test]
+    fn a_texture_map_on_an_object_uses_the_object_space_point() {
+        let mut sphere = Sphere::default();
+        sphere.set_transform(Matrix::translation(Tuple::vector(5., 0., 0.)));
+        let align = UvAlignCheck::new(Color::white(), Color::new(1., 0., 0.), Color::new(1., 1., 0.), Color::new(0., 1., 0.), Color::new(0., 1., 1.));
+        let pattern = Patterns::from(TextureMap::new(UvPatterns::from(align), UvMapping::Planar));
+
+        let color = pattern.color_at_object(&Object::from(sphere), Tuple::point(5.1, 0., 0.9));
+
+        assert_eq!(color, Color::new(1., 0., 0.));
+    }
+}
@@ -0,0 +1,228 @@
+use std::f64::consts::PI;
+use crate::color::Color;
+use crate::lights::Light;
+use crate::object::{Intersectable, Object, RayKind};
+use crate::ray::Ray;
+use crate::sampling::Rng;
+use crate::tuple::Tuple;
+use crate::world::{refract, World};
+
+const MAX_BOUNCES: usize = 8;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Photon {
+    pub position: Tuple,
+    // the way the photon was travelling when it landed
+    pub direction: Tuple,
+    pub power: Color
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct Node {
+    photon: Photon,
+    axis: usize,
+    left: Option<usize>,
+    right: Option<usize>
+}
+
+fn coordinate(point: Tuple, axis: usize) -> f64 {
+    match axis {
+        0 => point.x,
+        1 => point.y,
+        _ => point.z
+    }
+}
+
+// balanced on the median, cycling through x, y and z
+#[derive(Debug, PartialEq, Clone)]
+pub struct KdTree {
+    nodes: Vec<Node>,
+    root: Option<usize>
+}
+
+impl KdTree {
+    pub fn new(mut photons: Vec<Photon>) -> Self {
+        let mut nodes = Vec::with_capacity(photons.len());
+        let root = KdTree::build(&mut nodes, &mut photons, 0);
+        KdTree { nodes, root }
+    }
+
+    fn build(nodes: &mut Vec<Node>, photons: &mut [Photon], depth: usize) -> Option<usize> {
+        if photons.is_empty() {
+            return None;
+        }
+        let axis = depth % 3;
+        photons.sort_by(|a, b| coordinate(a.position, axis).total_cmp(&coordinate(b.position, axis)));
+        let median = photons.len() / 2;
+        let index = nodes.len();
+        nodes.push(Node { photon: photons[median], axis, left: None, right: None });
+
+        let (below, rest) = photons.split_at_mut(median);
+        let left = KdTree::build(nodes, below, depth + 1);
+        let right = KdTree::build(nodes, &mut rest[1..], depth + 1);
+        nodes[index].left = left;
+        nodes[index].right = right;
+        Some(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn within(&self, point: Tuple, radius: f64) -> Vec<Photon> {
+        let mut found = vec![];
+        let mut stack: Vec<usize> = self.root.into_iter().collect();
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if (node.photon.position - point).length() <= radius {
+                found.push(node.photon);
+            }
+            let offset = coordinate(point, node.axis) - coordinate(node.photon.position, node.axis);
+            let (near, far) = if offset < 0. { (node.left, node.right) } else { (node.right, node.left) };
+            if let Some(near) = near {
+                stack.push(near);
+            }
+            if let Some(far) = far {
+                if offset.abs() <= radius {
+                    stack.push(far);
+                }
+            }
+        }
+        found
+    }
+}
+
+// photons that reached a diffuse surface through at least one mirror or refraction
+#[derive(Debug, PartialEq, Clone)]
+pub struct PhotonMap {
+    pub tree: KdTree,
+    pub gather_radius: f64
+}
+
+fn is_specular(object: &Object, point: Tuple) -> bool {
+    let material = object.material_ref().at(object, point);
+    material.refracts() || material.reflective > 0. || material.conductor.is_some()
+}
+
+impl PhotonMap {
+    // point lights don't fall off with distance in this renderer, so each photon is scaled by the
+    // squared distance to its first hit to land with the same irradiance the lights give directly
+    pub fn build(world: &World, photon_count: usize, gather_radius: f64) -> Self {
+        let mut photons = vec![];
+        let per_light = photon_count / world.lights.len().max(1);
+        for (light_index, light) in world.lights.iter().enumerate() {
+            let mut rng = Rng::new(light_index as u64);
+            for _ in 0..per_light {
+                let z = 1. - 2. * rng.next_f64();
+                let radius = (1. - z * z).sqrt();
+                let phi = 2. * PI * rng.next_f64();
+                let direction = Tuple::vector(radius * phi.cos(), radius * phi.sin(), z);
+                let ray = Ray::new(light.position, direction);
+
+                // only photons heading for something specular can become caustics
+                let first = world.intersect_visible(ray, RayKind::Shadow).hit();
+                match first {
+                    Some(ref hit) if is_specular(&hit.object, ray.position(hit.t)) => {
+                        let power = light.intensity * (4. * PI / per_light as f64 * hit.t * hit.t);
+                        if let Some(photon) = PhotonMap::trace(world, light, ray, power, &mut rng) {
+                            photons.push(photon);
+                        }
+                    }
+                    _ => continue
+                }
+            }
+        }
+        PhotonMap { tree: KdTree::new(photons), gather_radius }
+    }
+
+    // the photon is dropped when the light is not linked to the surface it lands on
+    fn trace(world: &World, light: &Light, mut ray: Ray, mut power: Color, rng: &mut Rng) -> Option<Photon> {
+        for specular_bounces in 0..MAX_BOUNCES {
+            let xs = world.intersect_visible(ray, RayKind::Shadow);
+            let hit = xs.hit()?;
+            let comps = hit.prepare_computations(ray, &xs);
+            let material = &comps.material;
+            if comps.inside {
+                power = power * material.transmittance(comps.t * ray.direction.length());
+            }
+
+            if material.transparency > 0. {
+                let reflectance = if material.reflective > 0. { comps.schlick() } else { 0. };
+                let refracted = refract(comps.eye_v, comps.normal_v, comps.n1 / comps.n2);
+                ray = match refracted {
+                    Some(direction) if rng.next_f64() >= reflectance => {
+                        power = power * material.color_at(&comps.object, comps.point) * material.transparency;
+                        comps.spawn_ray(comps.under_point, direction)
+                    }
+                    _ => comps.spawn_ray(comps.over_point, comps.reflect_v)
+                };
+            } else if let Some(conductor) = material.conductor {
+                power = power * conductor.reflectance(comps.eye_v.dot(comps.normal_v));
+                ray = comps.spawn_ray(comps.over_point, comps.reflect_v);
+            } else if material.reflective > 0. && rng.next_f64() < material.reflective {
+                ray = comps.spawn_ray(comps.over_point, comps.reflect_v);
+            } else if specular_bounces > 0 && light.illuminates(comps.object_id, comps.object.name()) {
+                return Some(Photon { position: comps.point, direction: ray.direction.normalize(), power });
+            } else {
+                return None;
+            }
+        }
+        None
+    }
+
+    // irradiance from the photons arriving on the front of the surface inside the gather radius
+    pub fn irradiance(&self, point: Tuple, normal: Tuple) -> Color {
+        let area = PI * self.gather_radius * self.gather_radius;
+        self.tree.within(point, self.gather_radius).iter()
+            .filter(|photon| photon.direction.dot(normal) < 0.)
+            .fold(Color::black(), |total, photon| total + photon.power * (1. / area))
+    }
+}
+
+#[cfg(test)]
+mod tests_photon_map {
+    use crate::assert_equivalent;
+    use crate::equivalent::Equivalence;
+    use super::*;
+
+    fn photon_at(x: f64, y: f64, z: f64) -> Photon {
+        Photon { position: Tuple::point(x, y, z), direction: Tuple::vector(0., -1., 0.), power: Color::white() }
+    }
+
+    #[test]
+    fn a_radius_search_finds_the_same_photons_as_brute_force() {
+        let mut rng = Rng::new(1);
+        let photons: Vec<Photon> = (0..500)
+            .map(|_| photon_at(rng.next_f64() * 10., rng.next_f64() * 10., rng.next_f64() * 10.))
+            .collect();
+        let tree = KdTree::new(photons.clone());
+        let centre = Tuple::point(5., 5., 5.);
+
+        let mut found: Vec<f64> = tree.within(centre, 2.).iter().map(|photon| photon.position.x).collect();
+        let mut expected: Vec<f64> = photons.iter()
+            .filter(|photon| (photon.position - centre).length() <= 2.)
+            .map(|photon| photon.position.x)
+            .collect();
+        found.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        assert_eq!(tree.len(), 500);
+        assert!(!expected.is_empty());
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn irradiance_divides_the_gathered_power_by_the_disc_area() {
+        let map = PhotonMap {
+            tree: KdTree::new(vec![photon_at(0., 0., 0.), photon_at(0.5, 0., 0.), photon_at(2., 0., 0.)]),
+            gather_radius: 1.
+        };
+
+        assert_equivalent!(map.irradiance(Tuple::point(0., 0., 0.), Tuple::vector(0., 1., 0.)), Color::white() * (2. / PI));
+        assert_equivalent!(map.irradiance(Tuple::point(0., 0., 0.), Tuple::vector(0., -1., 0.)), Color::black());
+    }
+}
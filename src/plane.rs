@@ -2,18 +2,19 @@ use crate::EPSILON;
 use crate::intersection::{Intersection, Intersections};
 use crate::materials::Material;
 use crate::matrix::Matrix;
-use crate::object::{Intersectable, Object};
+use crate::object::{Intersectable, Object, Visibility};
 use crate::ray::Ray;
 use crate::tuple::Tuple;
+use crate::uv::planar_map;
 
-#[derive(Debug, PartialEq, Clone, Copy)]
-pub struct Plane { pub origin: Tuple, pub material: Material, pub transform: Matrix<4>}
+#[derive(Debug, PartialEq, Clone)]
+pub struct Plane { pub origin: Tuple, pub material: Material, pub transform: Matrix<4>, pub visibility: Visibility, pub name: Option<String>}
 
 impl Plane {
     pub fn new(origin: Tuple) -> Self {
         let transform = Matrix::identity();
         let material = Material::phong();
-        Plane { origin, material, transform }
+        Plane { origin, material, transform, visibility: Visibility::default(), name: None }
     }
 }
 
@@ -30,7 +31,7 @@ impl Intersectable for Plane {
         } else {
             let t = -local_ray.origin.y / local_ray.direction.y;
             Intersections::new(vec![
-                Intersection::new(t,  Object::from(*self))
+                Intersection::new(t,  Object::from(self.clone()))
             ])
         }
     }
@@ -39,8 +40,20 @@ impl Intersectable for Plane {
         Tuple::vector(0., 1., 0.)
     }
 
+    fn local_uv_at(&self, local_point: Tuple) -> (f64, f64) {
+        planar_map(local_point)
+    }
+
+    fn local_tangent_at(&self, _local_point: Tuple) -> Tuple {
+        Tuple::vector(1., 0., 0.)
+    }
+
     fn material(&self) -> Material {
-        self.material
+        self.material.clone()
+    }
+
+    fn material_ref(&self) -> &Material {
+        &self.material
     }
 
     fn transform(&self) -> Matrix<4> {
@@ -54,6 +67,22 @@ impl Intersectable for Plane {
     fn set_transform(&mut self, transform: Matrix<4>) {
         self.transform = transform
     }
+
+    fn visibility(&self) -> Visibility {
+        self.visibility
+    }
+
+    fn set_visibility(&mut self, visibility: Visibility) {
+        self.visibility = visibility
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    fn set_name(&mut self, name: &str) {
+        self.name = Some(name.to_string())
+    }
 }
 
 #[cfg(test)]
@@ -115,4 +144,13 @@ mod tests_plane {
         assert_eq!(xs.data[0].t, 1.);
         assert_eq!(xs.data[0].object , Object::from(plane));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn the_surface_coordinates_of_a_plane_repeat_every_unit() {
+        let plane = Plane::default();
+
+        assert_eq!(plane.local_uv_at(Tuple::point(0.25, 0., 0.5)), (0.25, 0.5));
+        assert_eq!(plane.local_uv_at(Tuple::point(1.25, 0., -0.5)), (0.25, 0.5));
+        assert_eq!(plane.local_tangent_at(Tuple::point(3., 0., 2.)), Tuple::vector(1., 0., 0.));
+    }
+}
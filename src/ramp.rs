@@ -0,0 +1,150 @@
+use crate::color::Color;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Interpolation {
+    Linear,
+    Smoothstep,
+    Constant,
+    Cubic
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ColorRamp {
+    pub stops: Vec<(f64, Color)>,
+    pub interpolation: Interpolation
+}
+
+impl ColorRamp {
+    pub fn new(mut stops: Vec<(f64, Color)>, interpolation: Interpolation) -> Self {
+        if stops.is_empty() {
+            panic!("Invalid args. ColorRamp needs at least one stop")
+        }
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        ColorRamp { stops, interpolation }
+    }
+
+    pub fn linear(color_a: Color, color_b: Color) -> Self {
+        ColorRamp::new(vec![(0., color_a), (1., color_b)], Interpolation::Linear)
+    }
+
+    pub fn first(&self) -> Color {
+        self.stops[0].1
+    }
+
+    pub fn last(&self) -> Color {
+        self.stops[self.stops.len() - 1].1
+    }
+
+    pub fn color_at(&self, position: f64) -> Color {
+        let (first, _) = self.stops[0];
+        let (last, _) = self.stops[self.stops.len() - 1];
+        if position <= first {
+            return self.first();
+        }
+        if position >= last {
+            return self.last();
+        }
+
+        let index = self.stops.iter().rposition(|&(stop, _)| stop <= position).unwrap();
+        let (p0, c0) = self.stops[index];
+        let (p1, c1) = self.stops[index + 1];
+        let fraction = (position - p0) / (p1 - p0);
+
+        match self.interpolation {
+            Interpolation::Constant => c0,
+            Interpolation::Linear => c0 + (c1 - c0) * fraction,
+            Interpolation::Smoothstep => {
+                let smooth = fraction * fraction * (3. - 2. * fraction);
+                c0 + (c1 - c0) * smooth
+            }
+            Interpolation::Cubic => {
+                // Catmull-Rom through the neighbouring stops, the end stops are repeated
+                let before = self.stops[index.saturating_sub(1)].1;
+                let after = self.stops[(index + 2).min(self.stops.len() - 1)].1;
+                let t = fraction;
+                let t2 = t * t;
+                let t3 = t2 * t;
+                (c0 * 2.
+                    + (c1 - before) * t
+                    + (before * 2. - c0 * 5. + c1 * 4. - after) * t2
+                    + (c0 * 3. - before - c1 * 3. + after) * t3) * 0.5
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_ramp {
+    use crate::assert_equivalent;
+    use crate::equivalent::Equivalence;
+    use super::*;
+
+    fn three_stops(interpolation: Interpolation) -> ColorRamp {
+        ColorRamp::new(vec![
+            (1., Color::new(0., 0., 1.)),
+            (0., Color::black()),
+            (0.5, Color::new(1., 0., 0.)),
+        ], interpolation)
+    }
+
+    #[test]
+    fn stops_are_sorted_by_position() {
+        let ramp = three_stops(Interpolation::Linear);
+
+        assert_eq!(ramp.stops[0].0, 0.);
+        assert_eq!(ramp.stops[1].0, 0.5);
+        assert_eq!(ramp.stops[2].0, 1.);
+    }
+
+    #[test]
+    fn a_nan_stop_sorts_last_instead_of_panicking() {
+        let ramp = ColorRamp::new(vec![(f64::NAN, Color::white()), (0., Color::black())], Interpolation::Linear);
+
+        assert_eq!(ramp.stops[0].0, 0.);
+        assert!(ramp.stops[1].0.is_nan());
+    }
+
+    #[test]
+    fn positions_outside_the_ramp_take_the_end_colors() {
+        let ramp = three_stops(Interpolation::Linear);
+
+        assert_equivalent!(ramp.color_at(-1.), Color::black());
+        assert_equivalent!(ramp.color_at(2.), Color::new(0., 0., 1.));
+    }
+
+    #[test]
+    fn a_linear_ramp_interpolates_between_neighbouring_stops() {
+        let ramp = three_stops(Interpolation::Linear);
+
+        assert_equivalent!(ramp.color_at(0.25), Color::new(0.5, 0., 0.));
+        assert_equivalent!(ramp.color_at(0.5), Color::new(1., 0., 0.));
+        assert_equivalent!(ramp.color_at(0.75), Color::new(0.5, 0., 0.5));
+    }
+
+    #[test]
+    fn a_constant_ramp_holds_the_previous_stop() {
+        let ramp = three_stops(Interpolation::Constant);
+
+        assert_equivalent!(ramp.color_at(0.49), Color::black());
+        assert_equivalent!(ramp.color_at(0.5), Color::new(1., 0., 0.));
+        assert_equivalent!(ramp.color_at(0.99), Color::new(1., 0., 0.));
+    }
+
+    #[test]
+    fn a_smoothstep_ramp_eases_in_and_out() {
+        let ramp = ColorRamp::new(vec![(0., Color::black()), (1., Color::white())], Interpolation::Smoothstep);
+
+        assert_equivalent!(ramp.color_at(0.25), Color::new(0.15625, 0.15625, 0.15625));
+        assert_equivalent!(ramp.color_at(0.5), Color::new(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn a_cubic_ramp_passes_through_every_stop() {
+        let ramp = three_stops(Interpolation::Cubic);
+
+        assert_equivalent!(ramp.color_at(0.), Color::black());
+        assert_equivalent!(ramp.color_at(0.5), Color::new(1., 0., 0.));
+        assert_equivalent!(ramp.color_at(1.), Color::new(0., 0., 1.));
+        assert_equivalent!(ramp.color_at(0.25), Color::new(0.5625, 0., -0.0625));
+    }
+}
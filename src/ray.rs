@@ -4,7 +4,10 @@ use crate::tuple::Tuple;
 #[derive(PartialEq, Clone, Copy, Debug)]
 pub struct Ray {
     pub origin: Tuple,
-    pub direction: Tuple
+    pub direction: Tuple,
+    pub wavelength: Option<f64>,
+    // set once the ray comes off a rough lobe, later rough bounces trace one sample instead of fanning out
+    pub scattered: bool
 }
 
 impl Ray {
@@ -12,7 +15,13 @@ impl Ray {
         if !origin.is_point() || !direction.is_vector() {
             panic!("Invalid args. to Ray. origin = Tuple::point | direction = Tuple::vector")
         }
-        Ray {origin, direction}
+        Ray {origin, direction, wavelength: None, scattered: false}
+    }
+
+    pub fn spectral(origin: Tuple, direction: Tuple, wavelength: f64) -> Self {
+        let mut ray = Ray::new(origin, direction);
+        ray.wavelength = Some(wavelength);
+        ray
     }
 
     pub fn position(&self, t: f64) -> Tuple {
@@ -23,6 +32,8 @@ impl Ray {
         Ray {
             origin: m * self.origin,
             direction: m * self.direction,
+            wavelength: self.wavelength,
+            scattered: self.scattered
         }
     }
 }
@@ -0,0 +1,105 @@
+use crate::ray::Ray;
+use crate::tuple::Tuple;
+
+// splitmix64, seeded from the shading point so renders stay deterministic across threads
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Rng {
+    state: u64
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    pub fn from_point(point: Tuple, salt: u64) -> Self {
+        let mut rng = Rng::new(salt);
+        rng.mix(point);
+        rng
+    }
+
+    // camera rays all share an origin, the direction is what tells the pixels apart
+    pub fn from_ray(ray: Ray, salt: u64) -> Self {
+        let mut rng = Rng::from_point(ray.origin, salt);
+        rng.mix(ray.direction);
+        rng
+    }
+
+    fn mix(&mut self, tuple: Tuple) {
+        for coordinate in [tuple.x, tuple.y, tuple.z] {
+            self.state ^= coordinate.to_bits();
+            self.next_u64();
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+pub fn orthonormal_basis(normal: Tuple) -> (Tuple, Tuple) {
+    let tangent = normal.perpendicular();
+    let bitangent = normal.cross(tangent);
+    (tangent, bitangent)
+}
+
+pub fn from_local(normal: Tuple, x: f64, y: f64, z: f64) -> Tuple {
+    let (tangent, bitangent) = orthonormal_basis(normal);
+    tangent * x + bitangent * y + normal * z
+}
+
+#[cfg(test)]
+mod tests_sampling {
+    use crate::assert_equivalent;
+    use crate::equivalent::Equivalence;
+    use super::*;
+
+    #[test]
+    fn the_same_seed_gives_the_same_sequence() {
+        let mut a = Rng::from_point(Tuple::point(1., 2., 3.), 7);
+        let mut b = Rng::from_point(Tuple::point(1., 2., 3.), 7);
+        let mut c = Rng::from_point(Tuple::point(1., 2., 3.001), 7);
+
+        let first = a.next_u64();
+        assert_eq!(first, b.next_u64());
+        assert_ne!(first, c.next_u64());
+    }
+
+    #[test]
+    fn rays_from_the_same_origin_are_seeded_by_their_direction() {
+        let origin = Tuple::point(0., 0., -5.);
+        let mut a = Rng::from_ray(Ray::new(origin, Tuple::vector(0., 0., 1.)), 11);
+        let mut b = Rng::from_ray(Ray::new(origin, Tuple::vector(0.1, 0., 1.)), 11);
+
+        assert_ne!(a.next_u64(), b.next_u64());
+    }
+
+    #[test]
+    fn floats_are_uniform_in_the_unit_interval() {
+        let mut rng = Rng::new(42);
+        let samples: Vec<f64> = (0..10000).map(|_| rng.next_f64()).collect();
+
+        assert!(samples.iter().all(|&x| (0. ..1.).contains(&x)));
+        let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+        assert!((mean - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn local_coordinates_follow_the_normal() {
+        let normal = Tuple::vector(1., 1., 0.).normalize();
+        let (tangent, bitangent) = orthonormal_basis(normal);
+
+        assert_equivalent!(tangent.dot(normal), 0.);
+        assert_equivalent!(bitangent.dot(normal), 0.);
+        assert_equivalent!(bitangent.length(), 1.);
+        assert_equivalent!(from_local(normal, 0., 0., 1.), normal);
+    }
+}
@@ -0,0 +1,174 @@
+use crate::color::Color;
+
+pub const MIN_WAVELENGTH: f64 = 380.;
+pub const MAX_WAVELENGTH: f64 = 720.;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Dispersion {
+    // n = a + b / wavelength^2, wavelength in micrometres
+    Cauchy { a: f64, b: f64 },
+    // n^2 = 1 + sum(b_i * wavelength^2 / (wavelength^2 - c_i)), wavelength in micrometres
+    Sellmeier { b: [f64; 3], c: [f64; 3] }
+}
+
+impl Dispersion {
+    pub fn cauchy(a: f64, b: f64) -> Self {
+        Dispersion::Cauchy { a, b }
+    }
+
+    pub fn sellmeier(b: [f64; 3], c: [f64; 3]) -> Self {
+        Dispersion::Sellmeier { b, c }
+    }
+
+    pub fn bk7() -> Self {
+        Dispersion::sellmeier(
+            [1.03961212, 0.231792344, 1.01046945],
+            [0.00600069867, 0.0200179144, 103.560653]
+        )
+    }
+
+    pub fn fused_silica() -> Self {
+        Dispersion::sellmeier(
+            [0.6961663, 0.4079426, 0.8974794],
+            [0.0046791, 0.0135121, 97.9340025]
+        )
+    }
+
+    pub fn refractive_index(&self, wavelength: f64) -> f64 {
+        let micrometres2 = (wavelength / 1000.).powi(2);
+        match *self {
+            Dispersion::Cauchy { a, b } => a + b / micrometres2,
+            Dispersion::Sellmeier { b, c } => {
+                let sum: f64 = b.iter().zip(c.iter())
+                    .map(|(b, c)| b * micrometres2 / (micrometres2 - c))
+                    .sum();
+                (1. + sum).sqrt()
+            }
+        }
+    }
+}
+
+fn lobe(wavelength: f64, mean: f64, below: f64, above: f64) -> f64 {
+    let sigma = if wavelength < mean { below } else { above };
+    (-0.5 * ((wavelength - mean) / sigma).powi(2)).exp()
+}
+
+// multi-lobe fit of the CIE 1931 observer (Wyman, Sloan and Shirley)
+pub fn wavelength_to_xyz(wavelength: f64) -> (f64, f64, f64) {
+    let x = 1.056 * lobe(wavelength, 599.8, 37.9, 31.0)
+        + 0.362 * lobe(wavelength, 442.0, 16.0, 26.7)
+        - 0.065 * lobe(wavelength, 501.1, 20.4, 26.2);
+    let y = 0.821 * lobe(wavelength, 568.8, 46.9, 40.5)
+        + 0.286 * lobe(wavelength, 530.9, 16.3, 31.1);
+    let z = 1.217 * lobe(wavelength, 437.0, 11.8, 36.0)
+        + 0.681 * lobe(wavelength, 459.0, 26.0, 13.8);
+    (x, y, z)
+}
+
+pub fn xyz_to_rgb(x: f64, y: f64, z: f64) -> Color {
+    Color::new(
+        3.2406 * x - 1.5372 * y - 0.4986 * z,
+        -0.9689 * x + 1.8758 * y + 0.0415 * z,
+        0.0557 * x - 0.2040 * y + 1.0570 * z
+    )
+}
+
+// linear rgb response of a single wavelength, out of gamut negatives are clipped
+pub fn wavelength_to_rgb(wavelength: f64) -> Color {
+    let (x, y, z) = wavelength_to_xyz(wavelength);
+    xyz_to_rgb(x, y, z).clamp(0., f64::INFINITY)
+}
+
+pub fn stratified_wavelength(index: usize, count: usize) -> f64 {
+    MIN_WAVELENGTH + (index as f64 + 0.5) / count as f64 * (MAX_WAVELENGTH - MIN_WAVELENGTH)
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Spectrum {
+    pub samples: Vec<(f64, Color)>
+}
+
+impl Spectrum {
+    pub fn new() -> Self {
+        Spectrum { samples: vec![] }
+    }
+
+    pub fn add_sample(&mut self, wavelength: f64, color: Color) {
+        self.samples.push((wavelength, color));
+    }
+
+    // white balanced against the sampled wavelengths, so a scene without dispersion renders as in rgb mode
+    pub fn to_rgb(&self) -> Color {
+        let mut total = Color::black();
+        let mut white = Color::black();
+        for &(wavelength, color) in self.samples.iter() {
+            let response = wavelength_to_rgb(wavelength);
+            total = total + color * response;
+            white = white + response;
+        }
+        let balance = |channel: f64, white: f64| if white > 0. { channel / white } else { 0. };
+        Color::new(
+            balance(total.red, white.red),
+            balance(total.green, white.green),
+            balance(total.blue, white.blue)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests_spectrum {
+    use crate::assert_equivalent;
+    use crate::equivalent::Equivalence;
+    use super::*;
+
+    #[test]
+    fn bk7_glass_has_its_catalogue_index() {
+        let bk7 = Dispersion::bk7();
+
+        assert!((bk7.refractive_index(587.6) - 1.5168).abs() < 0.0001);
+        assert!(bk7.refractive_index(450.) > bk7.refractive_index(650.));
+    }
+
+    #[test]
+    fn a_cauchy_index_bends_blue_more_than_red() {
+        let glass = Dispersion::cauchy(1.5046, 0.0042);
+
+        assert_equivalent!(glass.refractive_index(500.), 1.5214);
+        assert!(glass.refractive_index(400.) > glass.refractive_index(700.));
+    }
+
+    #[test]
+    fn single_wavelengths_map_to_their_hue() {
+        let red = wavelength_to_rgb(650.);
+        let green = wavelength_to_rgb(530.);
+        let blue = wavelength_to_rgb(450.);
+
+        assert!(red.red > red.green && red.red > red.blue);
+        assert!(green.green > green.red && green.green > green.blue);
+        assert!(blue.blue > blue.red && blue.blue > blue.green);
+    }
+
+    #[test]
+    fn a_flat_spectrum_is_white_balanced() {
+        let mut spectrum = Spectrum::new();
+        for i in 0..16 {
+            spectrum.add_sample(stratified_wavelength(i, 16), Color::new(0.2, 0.5, 0.8));
+        }
+
+        assert_equivalent!(spectrum.to_rgb(), Color::new(0.2, 0.5, 0.8));
+    }
+
+    #[test]
+    fn a_spectrum_lit_only_at_long_wavelengths_looks_red() {
+        let mut spectrum = Spectrum::new();
+        for i in 0..16 {
+            let wavelength = stratified_wavelength(i, 16);
+            let color = if wavelength > 600. { Color::white() } else { Color::black() };
+            spectrum.add_sample(wavelength, color);
+        }
+
+        let color = spectrum.to_rgb();
+        assert!(color.red > 0.3);
+        assert!(color.blue < 0.01);
+    }
+}
@@ -2,23 +2,24 @@ use crate::ray::Ray;
 use crate::intersection::{Intersection, Intersections};
 use crate::materials::Material;
 use crate::matrix::Matrix;
-use crate::object::{Intersectable, Object};
+use crate::object::{Intersectable, Object, Visibility};
 use crate::tuple::Tuple;
+use crate::uv::spherical_map;
 
-#[derive(Debug, PartialEq, Clone, Copy)]
-pub struct Sphere { pub origin: Tuple, pub material: Material, pub transform: Matrix<4>, pub radius: f64}
+#[derive(Debug, PartialEq, Clone)]
+pub struct Sphere { pub origin: Tuple, pub material: Material, pub transform: Matrix<4>, pub visibility: Visibility, pub name: Option<String>, pub radius: f64}
 
 impl Sphere {
     pub fn new(origin: Tuple, radius: f64) -> Self {
         let transform = Matrix::identity();
         let material = Material::phong();
-        Sphere { origin, material, transform, radius }
+        Sphere { origin, material, transform, visibility: Visibility::default(), name: None, radius }
     }
 
     pub fn grass(radius: f64) -> Self {
         let transform = Matrix::identity();
         let material = Material::glass();
-        Sphere { origin: Tuple::point(0., 0., 0.), material, transform, radius }
+        Sphere { origin: Tuple::point(0., 0., 0.), material, transform, visibility: Visibility::default(), name: None, radius }
     }
 }
 
@@ -43,8 +44,8 @@ impl Intersectable for Sphere {
             let t1 = (-b - discriminant.sqrt()) / (2. * a);
             let t2 = (-b + discriminant.sqrt()) / (2. * a);
             Intersections::new(vec![
-                Intersection::new(t1, Object::from(*self)),
-                Intersection::new(t2, Object::from(*self))
+                Intersection::new(t1, Object::from(self.clone())),
+                Intersection::new(t2, Object::from(self.clone()))
             ])
         }
     }
@@ -53,8 +54,22 @@ impl Intersectable for Sphere {
         (local_point - self.origin).normalize()
     }
 
+    fn local_uv_at(&self, local_point: Tuple) -> (f64, f64) {
+        let centered = local_point - self.origin;
+        spherical_map(Tuple::point(centered.x, centered.y, centered.z))
+    }
+
+    fn local_tangent_at(&self, local_point: Tuple) -> Tuple {
+        let centered = local_point - self.origin;
+        Tuple::vector(-centered.z, 0., centered.x)
+    }
+
     fn material(&self) -> Material {
-        self.material
+        self.material.clone()
+    }
+
+    fn material_ref(&self) -> &Material {
+        &self.material
     }
 
     fn transform(&self) -> Matrix<4> {
@@ -68,6 +83,22 @@ impl Intersectable for Sphere {
     fn set_transform(&mut self, transform: Matrix<4>) {
         self.transform = transform
     }
+
+    fn visibility(&self) -> Visibility {
+        self.visibility
+    }
+
+    fn set_visibility(&mut self, visibility: Visibility) {
+        self.visibility = visibility
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    fn set_name(&mut self, name: &str) {
+        self.name = Some(name.to_string())
+    }
 }
 
 #[cfg(test)]
@@ -276,7 +307,7 @@ mod tests_sphere {
         let mut material = Material::phong();
         material.ambient = 1.;
 
-        sphere.material = material;
+        sphere.material = material.clone();
 
         assert_eq!(sphere.material, material);
     }
@@ -288,4 +319,16 @@ mod tests_sphere {
         assert_eq!(sphere.material.transparency, 1.0);
         assert_eq!(sphere.material.reflactive_index, 1.5);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn the_tangent_on_a_sphere_follows_increasing_u() {
+        let sphere = Sphere::default();
+
+        assert_equivalent!(sphere.local_tangent_at(Tuple::point(0., 0., -1.)).normalize(), Tuple::vector(1., 0., 0.));
+        assert_equivalent!(sphere.local_tangent_at(Tuple::point(1., 0., 0.)).normalize(), Tuple::vector(0., 0., 1.));
+
+        let (u0, _) = sphere.local_uv_at(Tuple::point(0., 0., -1.));
+        let (u1, _) = sphere.local_uv_at(Tuple::point(0.01, 0., -1.));
+        assert!(u1 > u0);
+    }
+}
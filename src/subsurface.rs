@@ -0,0 +1,88 @@
+use std::f64::consts::PI;
+use crate::color::Color;
+use crate::sampling::{from_local, Rng};
+use crate::tuple::Tuple;
+
+// light enters where the eye ray hits, random walks through the interior and is lit where it leaves the object
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Subsurface {
+    // what survives each scattering event inside, per channel
+    pub color: Color,
+    // average distance between scattering events, in world units
+    pub mean_free_path: f64,
+    // share of the diffuse term that goes through the interior instead of bouncing off the surface
+    pub weight: f64,
+    pub samples: usize,
+    pub max_bounces: usize
+}
+
+impl Subsurface {
+    pub fn new(color: Color, mean_free_path: f64) -> Self {
+        if mean_free_path <= 0. {
+            panic!("Invalid args. The mean free path must be positive")
+        }
+        Subsurface { color, mean_free_path, weight: 1., samples: 16, max_bounces: 32 }
+    }
+
+    pub fn with_weight(mut self, weight: f64) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    pub fn with_samples(mut self, samples: usize) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    // exponentially distributed, u is uniform in [0, 1)
+    pub fn sample_distance(&self, u: f64) -> f64 {
+        -self.mean_free_path * (1. - u).ln()
+    }
+
+    // cosine weighted into the interior, normal points out of the object
+    pub fn sample_entry(&self, normal: Tuple, rng: &mut Rng) -> Tuple {
+        let radius = rng.next_f64().sqrt();
+        let phi = 2. * PI * rng.next_f64();
+        from_local(-normal, radius * phi.cos(), radius * phi.sin(), (1. - radius * radius).sqrt())
+    }
+
+    // isotropic, the interior has no preferred direction
+    pub fn sample_scatter(&self, rng: &mut Rng) -> Tuple {
+        let z = 1. - 2. * rng.next_f64();
+        let radius = (1. - z * z).sqrt();
+        let phi = 2. * PI * rng.next_f64();
+        Tuple::vector(radius * phi.cos(), radius * phi.sin(), z)
+    }
+}
+
+#[cfg(test)]
+mod tests_subsurface {
+    use crate::assert_equivalent;
+    use crate::equivalent::Equivalence;
+    use super::*;
+
+    #[test]
+    fn distances_between_scattering_events_average_the_mean_free_path() {
+        let subsurface = Subsurface::new(Color::white(), 0.3);
+        let mut rng = Rng::new(3);
+        let count = 20000;
+        let mean = (0..count).map(|_| subsurface.sample_distance(rng.next_f64())).sum::<f64>() / count as f64;
+
+        assert_equivalent!(subsurface.sample_distance(0.), 0.);
+        assert!((mean - 0.3).abs() < 0.01);
+    }
+
+    #[test]
+    fn walks_start_into_the_object() {
+        let subsurface = Subsurface::new(Color::white(), 1.);
+        let normal = Tuple::vector(0., 1., 0.);
+        let mut rng = Rng::new(5);
+
+        for _ in 0..100 {
+            let direction = subsurface.sample_entry(normal, &mut rng);
+            assert!(direction.dot(normal) < 0.);
+            assert_equivalent!(direction.length(), 1.);
+            assert_equivalent!(subsurface.sample_scatter(&mut rng).length(), 1.);
+        }
+    }
+}
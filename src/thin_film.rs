@@ -0,0 +1,109 @@
+use std::f64::consts::PI;
+use crate::color::Color;
+
+// wavelengths in nanometres standing in for the red, green and blue channels
+const RGB_WAVELENGTHS: [f64; 3] = [650., 532., 450.];
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ThinFilm {
+    // nanometres
+    pub thickness: f64,
+    pub ior: f64
+}
+
+fn amplitudes(n_i: f64, cos_i: f64, n_j: f64, cos_j: f64) -> (f64, f64) {
+    let s = (n_i * cos_i - n_j * cos_j) / (n_i * cos_i + n_j * cos_j);
+    let p = (n_j * cos_i - n_i * cos_j) / (n_j * cos_i + n_i * cos_j);
+    (s, p)
+}
+
+fn airy(r12: f64, r23: f64, cos_delta: f64) -> f64 {
+    let cross = 2. * r12 * r23 * cos_delta;
+    (r12 * r12 + r23 * r23 + cross) / (1. + r12 * r12 * r23 * r23 + cross)
+}
+
+impl ThinFilm {
+    pub fn new(thickness: f64, ior: f64) -> Self {
+        ThinFilm { thickness, ior }
+    }
+
+    // reflectance of a film between an outside medium n1 and a substrate n3, cos_i is taken in the outside medium
+    pub fn reflectance_at(&self, cos_i: f64, n1: f64, n3: f64, wavelength: f64) -> f64 {
+        let cos_i = cos_i.clamp(0., 1.);
+        let sin2_i = 1. - cos_i * cos_i;
+        let sin2_film = (n1 / self.ior).powi(2) * sin2_i;
+        let sin2_substrate = (n1 / n3).powi(2) * sin2_i;
+        if sin2_film > 1. || sin2_substrate > 1. {
+            return 1.;
+        }
+        let cos_film = (1. - sin2_film).sqrt();
+        let cos_substrate = (1. - sin2_substrate).sqrt();
+
+        let (r12_s, r12_p) = amplitudes(n1, cos_i, self.ior, cos_film);
+        let (r23_s, r23_p) = amplitudes(self.ior, cos_film, n3, cos_substrate);
+        let cos_delta = (4. * PI * self.ior * self.thickness * cos_film / wavelength).cos();
+
+        (airy(r12_s, r23_s, cos_delta) + airy(r12_p, r23_p, cos_delta)) / 2.
+    }
+
+    // a spectral ray sees its own wavelength in every channel
+    pub fn reflectance(&self, cos_i: f64, n1: f64, n3: f64, wavelength: Option<f64>) -> Color {
+        match wavelength {
+            Some(wavelength) => {
+                let reflectance = self.reflectance_at(cos_i, n1, n3, wavelength);
+                Color::new(reflectance, reflectance, reflectance)
+            }
+            None => Color::new(
+                self.reflectance_at(cos_i, n1, n3, RGB_WAVELENGTHS[0]),
+                self.reflectance_at(cos_i, n1, n3, RGB_WAVELENGTHS[1]),
+                self.reflectance_at(cos_i, n1, n3, RGB_WAVELENGTHS[2])
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_thin_film {
+    use crate::assert_equivalent;
+    use crate::equivalent::Equivalence;
+    use super::*;
+
+    #[test]
+    fn a_film_without_thickness_is_plain_fresnel() {
+        let film = ThinFilm::new(0., 1.38);
+
+        assert_equivalent!(film.reflectance_at(1., 1., 1.5, 532.), 0.04);
+    }
+
+    #[test]
+    fn a_quarter_wave_coating_cancels_its_design_wavelength() {
+        let ior = 1.5f64.sqrt();
+        let film = ThinFilm::new(532. / (4. * ior), ior);
+
+        let color = film.reflectance(1., 1., 1.5, None);
+
+        assert_equivalent!(color.green, 0.);
+        assert!(color.red > 0.001);
+        assert!(color.blue > 0.001);
+    }
+
+    #[test]
+    fn the_film_colour_shifts_with_the_viewing_angle() {
+        let film = ThinFilm::new(400., 1.33);
+
+        let head_on = film.reflectance(1., 1., 1., None);
+        let oblique = film.reflectance(0.5, 1., 1., None);
+
+        assert!(!head_on.equivalent(oblique));
+    }
+
+    #[test]
+    fn a_spectral_ray_sees_a_single_reflectance() {
+        let film = ThinFilm::new(400., 1.33);
+
+        let color = film.reflectance(0.8, 1., 1., Some(532.));
+
+        assert_equivalent!(color.red, color.blue);
+        assert_equivalent!(color.red, film.reflectance_at(0.8, 1., 1., 532.));
+    }
+}
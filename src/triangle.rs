@@ -2,11 +2,11 @@ use crate::EPSILON;
 use crate::intersection::{Intersection, Intersections};
 use crate::materials::Material;
 use crate::matrix::Matrix;
-use crate::object::{Intersectable, Object};
+use crate::object::{Intersectable, Object, Visibility};
 use crate::ray::Ray;
 use crate::tuple::Tuple;
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Triangle {
     pub p1: Tuple,
     pub p2: Tuple,
@@ -15,7 +15,9 @@ pub struct Triangle {
     pub e2: Tuple,
     pub normal: Tuple,
     pub material: Material,
-    pub transform: Matrix<4>
+    pub transform: Matrix<4>,
+    pub visibility: Visibility,
+    pub name: Option<String>
 }
 
 impl Triangle {
@@ -23,7 +25,7 @@ impl Triangle {
         let e1 = p2 - p1;
         let e2 = p3 - p1;
         let normal = e2.cross(e1).normalize();
-        Triangle { p1, p2, p3, e1, e2, normal, material: Material::phong(), transform: Matrix::identity() }
+        Triangle { p1, p2, p3, e1, e2, normal, material: Material::phong(), transform: Matrix::identity(), visibility: Visibility::default(), name: None }
     }
 }
 
@@ -55,7 +57,7 @@ impl Intersectable for Triangle {
         let t = f * self.e2.dot(origin_cross_e1);
 
         Intersections::new(vec![
-            Intersection::new(t, Object::from(*self))
+            Intersection::new(t, Object::from(self.clone()))
         ])
     }
 
@@ -63,8 +65,30 @@ impl Intersectable for Triangle {
         self.normal
     }
 
+    // barycentric weights of p2 and p3, the same u and v found by local_intersect
+    fn local_uv_at(&self, local_point: Tuple) -> (f64, f64) {
+        let p1_to_point = local_point - self.p1;
+        let d11 = self.e1.dot(self.e1);
+        let d12 = self.e1.dot(self.e2);
+        let d22 = self.e2.dot(self.e2);
+        let dp1 = p1_to_point.dot(self.e1);
+        let dp2 = p1_to_point.dot(self.e2);
+        let denominator = d11 * d22 - d12 * d12;
+        let u = (d22 * dp1 - d12 * dp2) / denominator;
+        let v = (d11 * dp2 - d12 * dp1) / denominator;
+        (u, v)
+    }
+
+    fn local_tangent_at(&self, _local_point: Tuple) -> Tuple {
+        self.e1
+    }
+
     fn material(&self) -> Material {
-        self.material
+        self.material.clone()
+    }
+
+    fn material_ref(&self) -> &Material {
+        &self.material
     }
 
     fn transform(&self) -> Matrix<4> {
@@ -78,6 +102,22 @@ impl Intersectable for Triangle {
     fn set_transform(&mut self, transform: Matrix<4>) {
         self.transform = transform
     }
+
+    fn visibility(&self) -> Visibility {
+        self.visibility
+    }
+
+    fn set_visibility(&mut self, visibility: Visibility) {
+        self.visibility = visibility
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    fn set_name(&mut self, name: &str) {
+        self.name = Some(name.to_string())
+    }
 }
 
 #[cfg(test)]
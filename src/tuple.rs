@@ -135,6 +135,11 @@ impl Tuple {
             self.x * other.y - self.y * other.x
         )
     }
+
+    pub fn perpendicular(&self) -> Self {
+        let axis = if self.x.abs() < 0.9 { Tuple::vector(1., 0., 0.) } else { Tuple::vector(0., 1., 0.) };
+        self.cross(axis).normalize()
+    }
 }
 
 #[cfg(test)]
@@ -340,4 +345,15 @@ mod tests_tuple {
 
         assert_equivalent!(r, Tuple::vector(1., 0., 0.));
     }
+
+    #[test]
+    fn a_perpendicular_vector_is_normalized_and_orthogonal() {
+        for v in [Tuple::vector(1., 0., 0.), Tuple::vector(0., 1., 0.), Tuple::vector(1., 2., 3.)].iter() {
+            let p = v.perpendicular();
+
+            assert_equivalent!(p.length(), 1.);
+            assert_equivalent!(p.dot(*v), 0.);
+            assert!(p.is_vector());
+        }
+    }
 }
@@ -0,0 +1,445 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+use crate::canvas::Canvas;
+use crate::color::Color;
+use crate::object::Object;
+use crate::tuple::Tuple;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum UvMapping {
+    Spherical,
+    Planar,
+    Cylindrical,
+    Cube
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CubeFace {
+    Left,
+    Right,
+    Front,
+    Back,
+    Up,
+    Down
+}
+
+impl UvMapping {
+    pub fn for_object(object: &Object) -> Self {
+        match *object {
+            Object::Sphere(_) => UvMapping::Spherical,
+            Object::Cube(_) => UvMapping::Cube,
+            Object::Cylinder(_) | Object::Cone(_) => UvMapping::Cylindrical,
+            Object::Plane(_) | Object::Triangle(_) => UvMapping::Planar,
+        }
+    }
+
+    pub fn uv_at(&self, point: Tuple) -> (f64, f64) {
+        match *self {
+            UvMapping::Spherical => spherical_map(point),
+            UvMapping::Planar => planar_map(point),
+            UvMapping::Cylindrical => cylindrical_map(point),
+            UvMapping::Cube => cube_map(point),
+        }
+    }
+}
+
+pub fn spherical_map(point: Tuple) -> (f64, f64) {
+    let theta = point.x.atan2(point.z);
+    let radius = Tuple::vector(point.x, point.y, point.z).length();
+    let phi = (point.y / radius).acos();
+    let raw_u = theta / (2. * PI);
+    let u = 1. - (raw_u + 0.5);
+    let v = 1. - phi / PI;
+    (u, v)
+}
+
+pub fn planar_map(point: Tuple) -> (f64, f64) {
+    (point.x.rem_euclid(1.), point.z.rem_euclid(1.))
+}
+
+pub fn cylindrical_map(point: Tuple) -> (f64, f64) {
+    let theta = point.x.atan2(point.z);
+    let raw_u = theta / (2. * PI);
+    let u = 1. - (raw_u + 0.5);
+    let v = point.y.rem_euclid(1.);
+    (u, v)
+}
+
+pub fn face_from_point(point: Tuple) -> CubeFace {
+    let abs_x = point.x.abs();
+    let coord = abs_x.max(point.y.abs()).max(point.z.abs());
+
+    if coord == point.x {
+        CubeFace::Right
+    } else if coord == -point.x {
+        CubeFace::Left
+    } else if coord == point.y {
+        CubeFace::Up
+    } else if coord == -point.y {
+        CubeFace::Down
+    } else if coord == point.z {
+        CubeFace::Front
+    } else {
+        CubeFace::Back
+    }
+}
+
+pub fn cube_face_uv(face: CubeFace, point: Tuple) -> (f64, f64) {
+    let (u, v) = match face {
+        CubeFace::Front => (point.x + 1., point.y + 1.),
+        CubeFace::Back => (1. - point.x, point.y + 1.),
+        CubeFace::Left => (point.z + 1., point.y + 1.),
+        CubeFace::Right => (1. - point.z, point.y + 1.),
+        CubeFace::Up => (point.x + 1., 1. - point.z),
+        CubeFace::Down => (point.x + 1., point.z + 1.),
+    };
+    (u.rem_euclid(2.) / 2., v.rem_euclid(2.) / 2.)
+}
+
+// Unwraps the six faces into a horizontal cross, four faces wide and three tall:
+// up on top, then left, front, right and back, and down at the bottom.
+pub fn cube_map(point: Tuple) -> (f64, f64) {
+    let face = face_from_point(point);
+    let (face_u, face_v) = cube_face_uv(face, point);
+    let (column, row) = match face {
+        CubeFace::Left => (0., 1.),
+        CubeFace::Front => (1., 1.),
+        CubeFace::Right => (2., 1.),
+        CubeFace::Back => (3., 1.),
+        CubeFace::Up => (1., 2.),
+        CubeFace::Down => (1., 0.),
+    };
+    ((column + face_u) / 4., (row + face_v) / 3.)
+}
+
+pub trait UvPattern {
+    fn uv_color_at(&self, u: f64, v: f64) -> Color;
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum UvPatterns {
+    Checkers(UvCheckers),
+    AlignCheck(UvAlignCheck),
+    Image(UvImage)
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct UvCheckers {
+    pub width: f64,
+    pub height: f64,
+    pub color_a: Color,
+    pub color_b: Color
+}
+
+impl From<UvCheckers> for UvPatterns {
+    fn from(checkers: UvCheckers) -> Self {
+        UvPatterns::Checkers(checkers)
+    }
+}
+
+impl UvCheckers {
+    pub fn new(width: f64, height: f64, color_a: Color, color_b: Color) -> Self {
+        UvCheckers { width, height, color_a, color_b }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct UvAlignCheck {
+    pub main: Color,
+    pub upper_left: Color,
+    pub upper_right: Color,
+    pub bottom_left: Color,
+    pub bottom_right: Color
+}
+
+impl From<UvAlignCheck> for UvPatterns {
+    fn from(align_check: UvAlignCheck) -> Self {
+        UvPatterns::AlignCheck(align_check)
+    }
+}
+
+impl UvAlignCheck {
+    pub fn new(main: Color, upper_left: Color, upper_right: Color, bottom_left: Color, bottom_right: Color) -> Self {
+        UvAlignCheck { main, upper_left, upper_right, bottom_left, bottom_right }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Filter {
+    Nearest,
+    Bilinear
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum WrapMode {
+    Repeat,
+    Clamp
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct UvImage {
+    pub canvas: Arc<Canvas>,
+    pub filter: Filter,
+    pub wrap: WrapMode
+}
+
+impl From<UvImage> for UvPatterns {
+    fn from(image: UvImage) -> Self {
+        UvPatterns::Image(image)
+    }
+}
+
+impl UvImage {
+    pub fn new(canvas: Canvas) -> Self {
+        UvImage { canvas: Arc::new(canvas), filter: Filter::Bilinear, wrap: WrapMode::Repeat }
+    }
+
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn with_wrap(mut self, wrap: WrapMode) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    fn wrap_index(&self, index: i64, size: usize) -> usize {
+        match self.wrap {
+            WrapMode::Repeat => index.rem_euclid(size as i64) as usize,
+            WrapMode::Clamp => index.clamp(0, size as i64 - 1) as usize,
+        }
+    }
+
+    fn texel(&self, x: i64, y: i64) -> Color {
+        let x = self.wrap_index(x, self.canvas.width);
+        let y = self.wrap_index(y, self.canvas.height);
+        self.canvas.get_pixel_color(x, y)
+    }
+}
+
+impl UvPattern for UvCheckers {
+    fn uv_color_at(&self, u: f64, v: f64) -> Color {
+        let u2 = (u * self.width).floor();
+        let v2 = (v * self.height).floor();
+        if (u2 + v2).rem_euclid(2.) == 0. {
+            self.color_a
+        } else {
+            self.color_b
+        }
+    }
+}
+
+impl UvPattern for UvAlignCheck {
+    fn uv_color_at(&self, u: f64, v: f64) -> Color {
+        if v > 0.8 {
+            if u < 0.2 {
+                return self.upper_left;
+            }
+            if u > 0.8 {
+                return self.upper_right;
+            }
+        } else if v < 0.2 {
+            if u < 0.2 {
+                return self.bottom_left;
+            }
+            if u > 0.8 {
+                return self.bottom_right;
+            }
+        }
+        self.main
+    }
+}
+
+impl UvPattern for UvImage {
+    // v runs bottom to top while canvas rows run top to bottom, texel centers sit at half coordinates
+    fn uv_color_at(&self, u: f64, v: f64) -> Color {
+        let x = u * self.canvas.width as f64 - 0.5;
+        let y = (1. - v) * self.canvas.height as f64 - 0.5;
+
+        match self.filter {
+            Filter::Nearest => self.texel(x.round() as i64, y.round() as i64),
+            Filter::Bilinear => {
+                let x0 = x.floor();
+                let y0 = y.floor();
+                let fx = x - x0;
+                let fy = y - y0;
+                let (x0, y0) = (x0 as i64, y0 as i64);
+
+                let top = self.texel(x0, y0) * (1. - fx) + self.texel(x0 + 1, y0) * fx;
+                let bottom = self.texel(x0, y0 + 1) * (1. - fx) + self.texel(x0 + 1, y0 + 1) * fx;
+                top * (1. - fy) + bottom * fy
+            }
+        }
+    }
+}
+
+impl UvPattern for UvPatterns {
+    fn uv_color_at(&self, u: f64, v: f64) -> Color {
+        match *self {
+            UvPatterns::Checkers(ref checkers) => checkers.uv_color_at(u, v),
+            UvPatterns::AlignCheck(ref align_check) => align_check.uv_color_at(u, v),
+            UvPatterns::Image(ref image) => image.uv_color_at(u, v),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_uv {
+    use std::f64::consts::FRAC_1_SQRT_2;
+    use crate::assert_equivalent;
+    use crate::equivalent::Equivalence;
+    use crate::cube::Cube;
+    use crate::plane::Plane;
+    use crate::sphere::Sphere;
+    use super::*;
+
+    fn assert_uv(actual: (f64, f64), expected: (f64, f64)) {
+        assert_equivalent!(actual.0, expected.0);
+        assert_equivalent!(actual.1, expected.1);
+    }
+
+    #[test]
+    fn checker_pattern_in_2d() {
+        let checkers = UvCheckers::new(2., 2., Color::black(), Color::white());
+
+        assert_eq!(checkers.uv_color_at(0.0, 0.0), Color::black());
+        assert_eq!(checkers.uv_color_at(0.5, 0.0), Color::white());
+        assert_eq!(checkers.uv_color_at(0.0, 0.5), Color::white());
+        assert_eq!(checkers.uv_color_at(0.5, 0.5), Color::black());
+        assert_eq!(checkers.uv_color_at(1.0, 1.0), Color::black());
+    }
+
+    #[test]
+    fn using_a_spherical_mapping_on_a_3d_point() {
+        assert_uv(spherical_map(Tuple::point(0., 0., -1.)), (0.0, 0.5));
+        assert_uv(spherical_map(Tuple::point(1., 0., 0.)), (0.25, 0.5));
+        assert_uv(spherical_map(Tuple::point(0., 0., 1.)), (0.5, 0.5));
+        assert_uv(spherical_map(Tuple::point(-1., 0., 0.)), (0.75, 0.5));
+        assert_uv(spherical_map(Tuple::point(0., 1., 0.)), (0.5, 1.0));
+        assert_uv(spherical_map(Tuple::point(0., -1., 0.)), (0.5, 0.0));
+        assert_uv(spherical_map(Tuple::point(FRAC_1_SQRT_2, FRAC_1_SQRT_2, 0.)), (0.25, 0.75));
+    }
+
+    #[test]
+    fn using_a_planar_mapping_on_a_3d_point() {
+        assert_uv(planar_map(Tuple::point(0.25, 0., 0.5)), (0.25, 0.5));
+        assert_uv(planar_map(Tuple::point(0.25, 0., -0.25)), (0.25, 0.75));
+        assert_uv(planar_map(Tuple::point(0.25, 0.5, -0.25)), (0.25, 0.75));
+        assert_uv(planar_map(Tuple::point(1.25, 0., 0.5)), (0.25, 0.5));
+        assert_uv(planar_map(Tuple::point(0.25, 0., -1.75)), (0.25, 0.25));
+        assert_uv(planar_map(Tuple::point(1., 0., -1.)), (0.0, 0.0));
+        assert_uv(planar_map(Tuple::point(0., 0., 0.)), (0.0, 0.0));
+    }
+
+    #[test]
+    fn using_a_cylindrical_mapping_on_a_3d_point() {
+        assert_uv(cylindrical_map(Tuple::point(0., 0., -1.)), (0.0, 0.0));
+        assert_uv(cylindrical_map(Tuple::point(0., 0.5, -1.)), (0.0, 0.5));
+        assert_uv(cylindrical_map(Tuple::point(0., 1., -1.)), (0.0, 0.0));
+        assert_uv(cylindrical_map(Tuple::point(FRAC_1_SQRT_2, 0.5, -FRAC_1_SQRT_2)), (0.125, 0.5));
+        assert_uv(cylindrical_map(Tuple::point(1., 0.5, 0.)), (0.25, 0.5));
+        assert_uv(cylindrical_map(Tuple::point(FRAC_1_SQRT_2, 0.5, FRAC_1_SQRT_2)), (0.375, 0.5));
+        assert_uv(cylindrical_map(Tuple::point(0., -0.25, 1.)), (0.5, 0.75));
+        assert_uv(cylindrical_map(Tuple::point(-FRAC_1_SQRT_2, 0.5, FRAC_1_SQRT_2)), (0.625, 0.5));
+        assert_uv(cylindrical_map(Tuple::point(-1., 1.25, 0.)), (0.75, 0.25));
+        assert_uv(cylindrical_map(Tuple::point(-FRAC_1_SQRT_2, 0.5, -FRAC_1_SQRT_2)), (0.875, 0.5));
+    }
+
+    #[test]
+    fn layout_of_the_align_check_pattern() {
+        let main = Color::white();
+        let ul = Color::new(1., 0., 0.);
+        let ur = Color::new(1., 1., 0.);
+        let bl = Color::new(0., 1., 0.);
+        let br = Color::new(0., 1., 1.);
+        let pattern = UvAlignCheck::new(main, ul, ur, bl, br);
+
+        assert_eq!(pattern.uv_color_at(0.5, 0.5), main);
+        assert_eq!(pattern.uv_color_at(0.1, 0.9), ul);
+        assert_eq!(pattern.uv_color_at(0.9, 0.9), ur);
+        assert_eq!(pattern.uv_color_at(0.1, 0.1), bl);
+        assert_eq!(pattern.uv_color_at(0.9, 0.1), br);
+    }
+
+    #[test]
+    fn identifying_the_face_of_a_cube_from_a_point() {
+        assert_eq!(face_from_point(Tuple::point(-1., 0.5, -0.25)), CubeFace::Left);
+        assert_eq!(face_from_point(Tuple::point(1.1, -0.75, 0.8)), CubeFace::Right);
+        assert_eq!(face_from_point(Tuple::point(0.1, 0.6, 0.9)), CubeFace::Front);
+        assert_eq!(face_from_point(Tuple::point(-0.7, 0., -2.)), CubeFace::Back);
+        assert_eq!(face_from_point(Tuple::point(0.5, 1., 0.9)), CubeFace::Up);
+        assert_eq!(face_from_point(Tuple::point(-0.2, -1.3, 1.1)), CubeFace::Down);
+    }
+
+    #[test]
+    fn uv_mapping_the_faces_of_a_cube() {
+        assert_uv(cube_face_uv(CubeFace::Front, Tuple::point(-0.5, 0.5, 1.)), (0.25, 0.75));
+        assert_uv(cube_face_uv(CubeFace::Front, Tuple::point(0.5, -0.5, 1.)), (0.75, 0.25));
+        assert_uv(cube_face_uv(CubeFace::Back, Tuple::point(0.5, 0.5, -1.)), (0.25, 0.75));
+        assert_uv(cube_face_uv(CubeFace::Back, Tuple::point(-0.5, -0.5, -1.)), (0.75, 0.25));
+        assert_uv(cube_face_uv(CubeFace::Left, Tuple::point(-1., 0.5, -0.5)), (0.25, 0.75));
+        assert_uv(cube_face_uv(CubeFace::Left, Tuple::point(-1., -0.5, 0.5)), (0.75, 0.25));
+        assert_uv(cube_face_uv(CubeFace::Right, Tuple::point(1., 0.5, 0.5)), (0.25, 0.75));
+        assert_uv(cube_face_uv(CubeFace::Right, Tuple::point(1., -0.5, -0.5)), (0.75, 0.25));
+        assert_uv(cube_face_uv(CubeFace::Up, Tuple::point(-0.5, 1., -0.5)), (0.25, 0.75));
+        assert_uv(cube_face_uv(CubeFace::Up, Tuple::point(0.5, 1., 0.5)), (0.75, 0.25));
+        assert_uv(cube_face_uv(CubeFace::Down, Tuple::point(-0.5, -1., 0.5)), (0.25, 0.75));
+        assert_uv(cube_face_uv(CubeFace::Down, Tuple::point(0.5, -1., -0.5)), (0.75, 0.25));
+    }
+
+    #[test]
+    fn the_cube_map_lays_the_faces_out_as_a_cross() {
+        assert_uv(cube_map(Tuple::point(0., 0., 1.)), (0.375, 0.5));
+        assert_uv(cube_map(Tuple::point(-1., 0., 0.)), (0.125, 0.5));
+        assert_uv(cube_map(Tuple::point(0., 0., -1.)), (0.875, 0.5));
+        assert_uv(cube_map(Tuple::point(0., 1., 0.)), (0.375, 0.83333));
+        assert_uv(cube_map(Tuple::point(0., -1., 0.)), (0.375, 0.16667));
+    }
+
+    #[test]
+    fn each_primitive_has_a_default_mapping() {
+        assert_eq!(UvMapping::for_object(&Object::from(Sphere::default())), UvMapping::Spherical);
+        assert_eq!(UvMapping::for_object(&Object::from(Plane::default())), UvMapping::Planar);
+        assert_eq!(UvMapping::for_object(&Object::from(Cube::default())), UvMapping::Cube);
+    }
+
+    fn two_by_two() -> Canvas {
+        let mut canvas = Canvas::new(2, 2);
+        canvas.set_pixel_color(0, 0, Color::new(1., 0., 0.));
+        canvas.set_pixel_color(1, 0, Color::new(0., 1., 0.));
+        canvas.set_pixel_color(0, 1, Color::new(0., 0., 1.));
+        canvas.set_pixel_color(1, 1, Color::white());
+        canvas
+    }
+
+    #[test]
+    fn nearest_filtering_picks_the_closest_texel() {
+        let image = UvImage::new(two_by_two()).with_filter(Filter::Nearest);
+
+        assert_eq!(image.uv_color_at(0.1, 0.9), Color::new(1., 0., 0.));
+        assert_eq!(image.uv_color_at(0.9, 0.9), Color::new(0., 1., 0.));
+        assert_eq!(image.uv_color_at(0.1, 0.1), Color::new(0., 0., 1.));
+        assert_eq!(image.uv_color_at(0.9, 0.1), Color::white());
+    }
+
+    #[test]
+    fn bilinear_filtering_blends_the_four_closest_texels() {
+        let image = UvImage::new(two_by_two()).with_wrap(WrapMode::Clamp);
+
+        assert_equivalent!(image.uv_color_at(0.25, 0.75), Color::new(1., 0., 0.));
+        assert_equivalent!(image.uv_color_at(0.5, 0.75), Color::new(0.5, 0.5, 0.));
+        assert_equivalent!(image.uv_color_at(0.5, 0.5), Color::new(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn wrap_modes_decide_what_lies_outside_the_image() {
+        let repeat = UvImage::new(two_by_two()).with_filter(Filter::Nearest);
+        let clamp = UvImage::new(two_by_two()).with_filter(Filter::Nearest).with_wrap(WrapMode::Clamp);
+
+        assert_eq!(repeat.uv_color_at(1.1, 0.9), Color::new(1., 0., 0.));
+        assert_eq!(clamp.uv_color_at(1.1, 0.9), Color::new(0., 1., 0.));
+        assert_eq!(repeat.uv_color_at(0.1, -0.1), Color::new(1., 0., 0.));
+        assert_eq!(clamp.uv_color_at(0.1, -0.1), Color::new(0., 0., 1.));
+    }
+}
@@ -0,0 +1,276 @@
+use crate::color::Color;
+use crate::EPSILON;
+use crate::matrix::Matrix;
+use crate::medium::Medium;
+use crate::object::Visibility;
+use crate::ray::Ray;
+use crate::sampling::Rng;
+use crate::tuple::Tuple;
+
+// densities on a regular lattice filling the volume's [-1, 1] box, x varies fastest
+#[derive(Debug, PartialEq, Clone)]
+pub struct DensityGrid {
+    pub width: usize,
+    pub height: usize,
+    pub depth: usize,
+    values: Vec<f64>
+}
+
+fn lattice_value(x: i64, y: i64, z: i64, seed: u64) -> f64 {
+    let hash = (x as u64).wrapping_mul(73856093) ^ (y as u64).wrapping_mul(19349663) ^ (z as u64).wrapping_mul(83492791);
+    Rng::new(seed ^ hash).next_f64()
+}
+
+fn value_noise(x: f64, y: f64, z: f64, seed: u64) -> f64 {
+    let smooth = |t: f64| t * t * (3. - 2. * t);
+    let (x0, y0, z0) = (x.floor(), y.floor(), z.floor());
+    let (fx, fy, fz) = (smooth(x - x0), smooth(y - y0), smooth(z - z0));
+    let (ix, iy, iz) = (x0 as i64, y0 as i64, z0 as i64);
+    let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+
+    let corner = |dx: i64, dy: i64, dz: i64| lattice_value(ix + dx, iy + dy, iz + dz, seed);
+    let plane = |dz: i64| lerp(
+        lerp(corner(0, 0, dz), corner(1, 0, dz), fx),
+        lerp(corner(0, 1, dz), corner(1, 1, dz), fx),
+        fy
+    );
+    lerp(plane(0), plane(1), fz)
+}
+
+impl DensityGrid {
+    pub fn new(width: usize, height: usize, depth: usize) -> Self {
+        if width == 0 || height == 0 || depth == 0 {
+            panic!("Invalid args. A density grid needs at least one voxel along each axis")
+        }
+        DensityGrid { width, height, depth, values: vec![0.; width * height * depth] }
+    }
+
+    // whitespace separated: width height depth, then one density per voxel, # starts a comment
+    pub fn from_text(data: &[u8]) -> Result<Self, String> {
+        let text = String::from_utf8_lossy(data);
+        let mut tokens = text
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or(""))
+            .flat_map(|line| line.split_whitespace());
+
+        let mut next_number = || -> Result<f64, String> {
+            let token = tokens.next().ok_or("Invalid density grid. Unexpected end of data")?;
+            token.parse::<f64>().map_err(|_| format!("Invalid density grid. {} is not a number", token))
+        };
+
+        let width = next_number()? as usize;
+        let height = next_number()? as usize;
+        let depth = next_number()? as usize;
+        if width == 0 || height == 0 || depth == 0 {
+            return Err(String::from("Invalid density grid. Every dimension must be positive"));
+        }
+
+        let mut grid = DensityGrid::new(width, height, depth);
+        for value in grid.values.iter_mut() {
+            *value = next_number()?;
+        }
+        Ok(grid)
+    }
+
+    // one byte per voxel, 255 is a density of one
+    pub fn from_raw(width: usize, height: usize, depth: usize, data: &[u8]) -> Result<Self, String> {
+        if data.len() != width * height * depth {
+            return Err(format!("Invalid density grid. Expected {} bytes, got {}", width * height * depth, data.len()));
+        }
+        let mut grid = DensityGrid::new(width, height, depth);
+        for (value, byte) in grid.values.iter_mut().zip(data.iter()) {
+            *value = *byte as f64 / 255.;
+        }
+        Ok(grid)
+    }
+
+    // fractal value noise, frequency is the number of noise cells across the grid
+    pub fn from_noise(size: usize, frequency: f64, octaves: usize, seed: u64) -> Self {
+        let mut grid = DensityGrid::new(size, size, size);
+        for z in 0..size {
+            for y in 0..size {
+                for x in 0..size {
+                    let mut total = 0.;
+                    let mut amplitude = 0.5;
+                    let mut scale = frequency / size as f64;
+                    for octave in 0..octaves {
+                        total += amplitude * value_noise(x as f64 * scale, y as f64 * scale, z as f64 * scale, seed + octave as u64);
+                        amplitude *= 0.5;
+                        scale *= 2.;
+                    }
+                    grid.set(x, y, z, total);
+                }
+            }
+        }
+        grid
+    }
+
+    pub fn get(&self, x: usize, y: usize, z: usize) -> f64 {
+        self.values[(z * self.height + y) * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, z: usize, density: f64) {
+        self.values[(z * self.height + y) * self.width + x] = density;
+    }
+
+    pub fn max_density(&self) -> f64 {
+        self.values.iter().cloned().fold(0., f64::max)
+    }
+
+    // trilinear between voxel centres, zero outside the box
+    pub fn density_at(&self, local_point: Tuple) -> f64 {
+        let coordinates = [local_point.x, local_point.y, local_point.z];
+        if coordinates.iter().any(|c| c.abs() > 1.) {
+            return 0.;
+        }
+        let sizes = [self.width, self.height, self.depth];
+        let mut lower = [0; 3];
+        let mut upper = [0; 3];
+        let mut fraction = [0.; 3];
+        for axis in 0..3 {
+            let position = ((coordinates[axis] + 1.) / 2. * sizes[axis] as f64 - 0.5).clamp(0., (sizes[axis] - 1) as f64);
+            lower[axis] = position.floor() as usize;
+            upper[axis] = (lower[axis] + 1).min(sizes[axis] - 1);
+            fraction[axis] = position - lower[axis] as f64;
+        }
+
+        let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+        let row = |y: usize, z: usize| lerp(self.get(lower[0], y, z), self.get(upper[0], y, z), fraction[0]);
+        let slice = |z: usize| lerp(row(lower[1], z), row(upper[1], z), fraction[1]);
+        lerp(slice(lower[2]), slice(upper[2]), fraction[2])
+    }
+}
+
+// a box of participating media, the grid density scales the medium's coefficients. It has no surface, the world
+// marches it through span, and the transform is private so its cached inverse stays in step
+#[derive(Debug, PartialEq, Clone)]
+pub struct Volume {
+    transform: Matrix<4>, inverse: Matrix<4>, pub visibility: Visibility, pub grid: DensityGrid, pub medium: Medium
+}
+
+impl Volume {
+    pub fn new(grid: DensityGrid, medium: Medium) -> Self {
+        let transform = Matrix::identity();
+        Volume { transform, inverse: transform.inverse(), visibility: Visibility::default(), grid, medium }
+    }
+
+    pub fn transform(&self) -> Matrix<4> {
+        self.transform
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix<4>) {
+        self.transform = transform;
+        self.inverse = transform.inverse();
+    }
+
+    fn slab(origin: f64, direction: f64) -> (f64, f64) {
+        if direction.abs() < EPSILON {
+            return if origin.abs() <= 1. { (f64::NEG_INFINITY, f64::INFINITY) } else { (f64::INFINITY, f64::NEG_INFINITY) };
+        }
+        let a = (-1. - origin) / direction;
+        let b = (1. - origin) / direction;
+        (a.min(b), a.max(b))
+    }
+
+    fn local_span(local_ray: Ray) -> Option<(f64, f64)> {
+        let axes = [
+            Volume::slab(local_ray.origin.x, local_ray.direction.x),
+            Volume::slab(local_ray.origin.y, local_ray.direction.y),
+            Volume::slab(local_ray.origin.z, local_ray.direction.z)
+        ];
+        let enter = axes.iter().map(|axis| axis.0).fold(f64::NEG_INFINITY, f64::max);
+        let leave = axes.iter().map(|axis| axis.1).fold(f64::INFINITY, f64::min);
+        if enter > leave {
+            return None;
+        }
+        Some((enter, leave))
+    }
+
+    // where a world ray enters and leaves the box
+    pub fn span(&self, ray: Ray) -> Option<(f64, f64)> {
+        Volume::local_span(ray.set_transform(self.inverse))
+    }
+
+    pub fn density_at(&self, world_point: Tuple) -> f64 {
+        self.grid.density_at(self.inverse * world_point)
+    }
+
+    // ray marched over the part of the box between t_min and t_max
+    pub fn transmittance(&self, ray: Ray, t_min: f64, t_max: f64) -> Color {
+        let (enter, leave) = match self.span(ray) {
+            Some(span) => span,
+            None => return Color::white()
+        };
+        let start = enter.max(t_min);
+        let end = leave.min(t_max);
+        if start >= end {
+            return Color::white();
+        }
+
+        let samples = self.medium.samples.max(1);
+        let step = (end - start) / samples as f64;
+        let length = ray.direction.length();
+        let optical_depth: f64 = (0..samples)
+            .map(|i| self.density_at(ray.position(start + (i as f64 + 0.5) * step)))
+            .sum::<f64>() * step * length;
+        self.medium.transmittance(optical_depth)
+    }
+}
+
+#[cfg(test)]
+mod tests_volume {
+    use crate::assert_equivalent;
+    use crate::equivalent::Equivalence;
+    use super::*;
+
+    #[test]
+    fn reading_a_density_grid_from_text() {
+        let grid = DensityGrid::from_text(b"# smoke\n2 1 2\n0 0.25\n0.5 1\n").unwrap();
+
+        assert_eq!((grid.width, grid.height, grid.depth), (2, 1, 2));
+        assert_equivalent!(grid.get(1, 0, 0), 0.25);
+        assert_equivalent!(grid.get(0, 0, 1), 0.5);
+        assert_equivalent!(grid.max_density(), 1.);
+        assert!(DensityGrid::from_text(b"2 2 2\n0 1").is_err());
+    }
+
+    #[test]
+    fn reading_a_raw_density_grid() {
+        let grid = DensityGrid::from_raw(2, 1, 1, &[0, 255]).unwrap();
+
+        assert_equivalent!(grid.get(1, 0, 0), 1.);
+        assert!(DensityGrid::from_raw(2, 2, 2, &[0, 255]).is_err());
+    }
+
+    #[test]
+    fn densities_are_interpolated_between_voxel_centres() {
+        let grid = DensityGrid::from_text(b"2 1 1  0 1").unwrap();
+
+        assert_equivalent!(grid.density_at(Tuple::point(-0.5, 0., 0.)), 0.);
+        assert_equivalent!(grid.density_at(Tuple::point(0., 0., 0.)), 0.5);
+        assert_equivalent!(grid.density_at(Tuple::point(0.9, 0.3, -0.7)), 1.);
+        assert_equivalent!(grid.density_at(Tuple::point(1.5, 0., 0.)), 0.);
+    }
+
+    #[test]
+    fn noise_grids_are_deterministic_and_vary() {
+        let a = DensityGrid::from_noise(8, 2., 3, 1);
+        let b = DensityGrid::from_noise(8, 2., 3, 1);
+
+        assert_eq!(a, b);
+        assert!(a.max_density() <= 1.);
+        assert!(a.get(0, 0, 0) != a.get(5, 3, 6));
+    }
+
+    #[test]
+    fn a_volume_follows_its_transform() {
+        let mut volume = Volume::new(DensityGrid::from_text(b"1 1 1 1").unwrap(), Medium::new(Color::white(), Color::black()));
+        volume.set_transform(Matrix::translation(Tuple::vector(0., 0., 5.)) * Matrix::scaling(Tuple::vector(2., 2., 2.)));
+        let ray = Ray::new(Tuple::point(0., 0., 0.), Tuple::vector(0., 0., 1.));
+
+        assert_eq!(volume.span(ray), Some((3., 7.)));
+        assert_equivalent!(volume.density_at(Tuple::point(0., 1., 5.)), 1.);
+        assert_equivalent!(volume.transmittance(ray, 0., 100.), Color::white() * (-4f64).exp());
+        assert_equivalent!(volume.transmittance(ray, 0., 4.), Color::white() * (-1f64).exp());
+    }
+}
@@ -1,124 +1,449 @@
 use crate::color::Color;
 use crate::equivalent::Equivalence;
-use crate::intersection::{Computations, Intersections};
+use crate::intersection::{Computations, Intersection, Intersections};
 use crate::lights::Light;
-use crate::object::{Intersectable, Object};
+use crate::materials::Material;
+use crate::medium::Medium;
+use crate::microfacet::{ggx_sample_weight, sample_ggx_half_vector};
+use crate::object::{Intersectable, Object, RayKind};
+use crate::occlusion::AmbientOcclusion;
+use crate::photon_map::PhotonMap;
 use crate::ray::Ray;
+use crate::sampling::Rng;
+use crate::subsurface::Subsurface;
 use crate::tuple::Tuple;
+use crate::volume::Volume;
+use crate::EPSILON;
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct World {
     pub objects: Vec<Object>,
     pub lights: Vec<Light>,
+    // marched along rays instead of intersected
+    pub volumes: Vec<Volume>,
+    pub medium: Option<Medium>,
+    pub ambient_occlusion: Option<AmbientOcclusion>,
+    pub caustics: Option<PhotonMap>,
 }
 
 impl World {
     pub fn new(objects: Vec<Object>, lights: Vec<Light>) -> Self {
-        World { objects, lights }
+        World { objects, lights, volumes: vec![], medium: None, ambient_occlusion: None, caustics: None }
+    }
+
+    pub fn with_volume(mut self, volume: Volume) -> Self {
+        self.volumes.push(volume);
+        self
+    }
+
+    pub fn with_medium(mut self, medium: Medium) -> Self {
+        self.medium = Some(medium);
+        self
+    }
+
+    pub fn with_ambient_occlusion(mut self, ambient_occlusion: AmbientOcclusion) -> Self {
+        self.ambient_occlusion = Some(ambient_occlusion);
+        self
+    }
+
+    // shoots the photons from the scene as it is now, rebuild after moving objects or lights
+    pub fn with_caustics(mut self, photon_count: usize, gather_radius: f64) -> Self {
+        self.caustics = Some(PhotonMap::build(&self, photon_count, gather_radius));
+        self
     }
 
     pub fn intersect_world(&self, ray: Ray) -> Intersections {
         let mut xs = vec![];
-        for object in self.objects.iter() {
-            xs.extend(object.intersect(ray));
+        for (id, object) in self.objects.iter().enumerate() {
+            xs.extend(object.intersect(ray).data.into_iter().map(|hit| Intersection { object_id: Some(id), ..hit }));
         }
         Intersections::new(xs)
     }
 
-    pub fn shade_hit(self, comps: Computations, remaining: u8) -> Color {
-        let mut surface = Color::black();
-        for &light in self.lights.iter() {
-            let shadowed = self.clone().is_shadowed(comps.over_point, light);
-            let color = comps.object.material().lighting(comps.object, light, comps.over_point, comps.eye_v, comps.normal_v, shadowed);
-            surface = surface + color;
+    pub fn intersect_visible(&self, ray: Ray, kind: RayKind) -> Intersections {
+        let mut xs = vec![];
+        for (id, object) in self.objects.iter().enumerate().filter(|(_, object)| object.visibility().allows(kind)) {
+            xs.extend(object.intersect(ray).data.into_iter().map(|hit| Intersection { object_id: Some(id), ..hit }));
+        }
+        Intersections::new(xs)
+    }
+
+    pub fn shade_hit(&self, comps: &Computations, remaining: u8) -> Color {
+        let mut surface = if comps.inside { Color::black() } else { comps.material.emission };
+        let name = comps.object.name();
+        let lights: Vec<&Light> = self.lights.iter().filter(|light| light.illuminates(comps.object_id, name)).collect();
+
+        let material: &Material = &comps.material;
+        let occlusion = match self.ambient_occlusion {
+            Some(ambient_occlusion) => self.occlusion_at(comps.over_point, comps.normal_v, &ambient_occlusion),
+            None => 1.
+        };
+        let mut diffuse = material.diffuse;
+        if let Some(subsurface) = material.subsurface {
+            diffuse *= 1. - subsurface.weight;
+            let albedo = material.color_at(&comps.object, comps.point) * (material.diffuse * subsurface.weight);
+            surface = surface + albedo * self.subsurface_color(comps, &subsurface, &lights);
+        }
+        let surface_material;
+        let lit = if occlusion < 1. || diffuse != material.diffuse {
+            surface_material = Material { ambient: material.ambient * occlusion, diffuse, ..material.clone() };
+            &surface_material
+        } else {
+            material
+        };
+        for light in lights {
+            let transmission = self.shadow_transmission(comps.over_point, light);
+            surface = surface + lit.lighting_through(&comps.object, light, comps.over_point, comps.eye_v, comps.normal_v, transmission);
+        }
+        if let Some(caustics) = &self.caustics {
+            let albedo = lit.color_at(&comps.object, comps.point) * lit.diffuse;
+            surface = surface + albedo * caustics.irradiance(comps.point, comps.normal_v);
+        }
+
+        let scattered = self.scattered_color(comps, material, remaining);
+        if let Some(coat) = material.clearcoat {
+            let reflectance = coat.fresnel(comps.eye_v.dot(comps.normal_v));
+            let coat_material = Material { roughness: coat.roughness, ..material.clone() };
+            return surface + scattered * (1. - reflectance) + self.mirror_color(comps, &coat_material, remaining) * reflectance;
+        }
+        surface + scattered
+    }
+
+    // the share of hemisphere rays from the point that escape further than max_distance
+    pub fn occlusion_at(&self, point: Tuple, normal: Tuple, ambient_occlusion: &AmbientOcclusion) -> f64 {
+        let mut rng = Rng::from_point(point, 17);
+        let mut open = 0;
+        for _ in 0..ambient_occlusion.samples {
+            let ray = Ray::new(point, ambient_occlusion.sample_direction(normal, &mut rng));
+            let blocked = self.intersect_visible(ray, RayKind::Shadow).data.iter()
+                .any(|hit| hit.t > 0. && hit.t < ambient_occlusion.max_distance);
+            if !blocked {
+                open += 1;
+            }
         }
-        let reflected = self.clone().reflected_color(comps, remaining);
-        let refracted = self.clone().refracted_color(comps, remaining);
+        open as f64 / ambient_occlusion.samples as f64
+    }
 
-        let material = comps.object.material();
+    // each walk enters under the hit, scatters inside and picks up the diffuse light falling where it leaves
+    fn subsurface_color(&self, comps: &Computations, subsurface: &Subsurface, lights: &[&Light]) -> Color {
+        let mut rng = Rng::from_point(comps.point, 5);
+        let mut total = Color::black();
+        for _ in 0..subsurface.samples {
+            let mut point = comps.under_point;
+            let mut direction = subsurface.sample_entry(comps.normal_v, &mut rng);
+            let mut throughput = Color::white();
+            for _ in 0..subsurface.max_bounces {
+                let distance = subsurface.sample_distance(rng.next_f64());
+                let exit = comps.object.intersect(comps.spawn_ray(point, direction)).data.into_iter().find(|hit| hit.t > EPSILON);
+                match exit {
+                    Some(exit) if exit.t <= distance => {
+                        let exit_point = point + direction * exit.t;
+                        let normal = comps.object.normal_at(exit_point);
+                        total = total + throughput * self.irradiance(exit_point + normal * EPSILON, normal, lights);
+                        break;
+                    }
+                    Some(_) => {
+                        point = point + direction * distance;
+                        throughput = throughput * subsurface.color;
+                        direction = subsurface.sample_scatter(&mut rng);
+                    }
+                    None => break
+                }
+            }
+        }
+        total * (1. / subsurface.samples.max(1) as f64)
+    }
+
+    fn irradiance(&self, point: Tuple, normal: Tuple, lights: &[&Light]) -> Color {
+        let mut total = Color::black();
+        for light in lights {
+            let cos = (light.position - point).normalize().dot(normal);
+            if cos > 0. {
+                total = total + light.intensity * self.shadow_transmission(point, light) * cos;
+            }
+        }
+        total
+    }
+
+    fn scattered_color(&self, comps: &Computations, material: &Material, remaining: u8) -> Color {
+        if let Some(conductor) = material.conductor {
+            let reflectance = conductor.reflectance(comps.eye_v.dot(comps.normal_v));
+            return self.mirror_color(comps, material, remaining) * reflectance;
+        }
+
+        let reflected = self.reflected_color(comps, remaining);
+        let refracted = self.refracted_color(comps, remaining);
         if material.reflective > 0. && material.transparency > 0. {
-            let reflectance = comps.schlick();
-            return surface + reflected * reflectance + refracted * (1. - reflectance);
+            let reflectance = comps.reflectance();
+            return reflected * reflectance + refracted * (Color::white() - reflectance);
+        }
+        if material.thin_film.is_some() {
+            return reflected * comps.reflectance() + refracted;
         }
 
-        surface + reflected + refracted
+        reflected + refracted
     }
 
-    pub fn reflected_color(self, comps: Computations, remaining: u8) -> Color {
-        if (comps.object.material().reflective).equivalent(0.) || remaining <= 0 {
+    pub fn reflected_color(&self, comps: &Computations, remaining: u8) -> Color {
+        let material = &comps.material;
+        if (material.reflective).equivalent(0.) {
             return Color::black()
         }
-        let reflect_ray = Ray::new(comps.over_point, comps.reflect_v);
-        let color = self.color_at(reflect_ray, remaining - 1);
-        color * comps.object.material().reflective
+        self.mirror_color(comps, material, remaining) * material.reflective
     }
 
-    pub fn refracted_color(self, comps: Computations, remaining: u8) -> Color {
-        if (comps.object.material().transparency).equivalent(0.) || remaining <= 0 {
+    fn mirror_color(&self, comps: &Computations, material: &Material, remaining: u8) -> Color {
+        if remaining <= 0 {
+            return Color::black()
+        }
+        let perfect = comps.spawn_ray(comps.over_point, comps.reflect_v);
+        self.sample_lobe(comps, material, perfect, RayKind::Reflection, remaining, |half_vector| {
+            let direction = (-comps.eye_v).reflect(half_vector);
+            if direction.dot(comps.normal_v) > 0. { Some(comps.spawn_ray(comps.over_point, direction)) } else { None }
+        })
+    }
+
+    pub fn refracted_color(&self, comps: &Computations, remaining: u8) -> Color {
+        let material = &comps.material;
+        if (material.transparency).equivalent(0.) || remaining <= 0 {
             return Color::black();
         }
 
         let n_ratio = comps.n1 / comps.n2;
-        let cos_i = comps.eye_v.dot(comps.normal_v);
-        let sin2_t = n_ratio.powi(2) * (1. - cos_i.powi(2));
-        if sin2_t > 1. {
-            return Color::black();
+        let direction = match refract(comps.eye_v, comps.normal_v, n_ratio) {
+            Some(direction) => direction,
+            None => return Color::black()
+        };
+        let perfect = comps.spawn_ray(comps.under_point, direction);
+        let color = self.sample_lobe(comps, material, perfect, RayKind::Refraction, remaining, |half_vector| {
+            refract(comps.eye_v, half_vector, n_ratio)
+                .filter(|direction| direction.dot(comps.normal_v) < 0.)
+                .map(|direction| comps.spawn_ray(comps.under_point, direction))
+        });
+        color * material.transparency
+    }
+
+    // estimates the material's GGX lobe around the perfect ray, only the first rough bounce fans out into several samples
+    fn sample_lobe<F>(&self, comps: &Computations, material: &Material, perfect: Ray, kind: RayKind, remaining: u8, spawn: F) -> Color
+        where F: Fn(Tuple) -> Option<Ray> {
+        if material.roughness.equivalent(0.) || material.glossy_samples <= 1 {
+            return self.trace(perfect, kind, remaining - 1);
         }
 
-        let cos_t = (1.0 - sin2_t).sqrt();
-        let direction = comps.normal_v * (n_ratio * cos_i - cos_t) - comps.eye_v * n_ratio;
-        let refract_ray = Ray::new(comps.under_point, direction);
-        self.color_at(refract_ray, remaining - 1) * comps.object.material().transparency
+        let samples = if comps.scattered { 1 } else { material.glossy_samples };
+        let mut rng = Rng::from_point(comps.point, remaining as u64);
+        let mut total = Color::black();
+        for _ in 0..samples {
+            let half_vector = sample_ggx_half_vector(comps.normal_v, material.roughness, rng.next_f64(), rng.next_f64());
+            // samples leaving the surface on the wrong side carry no light
+            if let Some(ray) = spawn(half_vector) {
+                let weight = ggx_sample_weight(comps.normal_v, half_vector, comps.eye_v, ray.direction, material.roughness);
+                total = total + self.trace(Ray { scattered: true, ..ray }, kind, remaining - 1) * weight;
+            }
+        }
+        total * (1. / samples as f64)
     }
 
-    pub fn is_shadowed(self, point: Tuple, light: Light) -> bool {
+    pub fn is_shadowed(&self, point: Tuple, light: &Light) -> bool {
+        self.shadow_transmission(point, light) == Color::black()
+    }
+
+    // light left after passing every surface between the point and the light, transparent objects tint it by their color
+    pub fn shadow_transmission(&self, point: Tuple, light: &Light) -> Color {
         let shadow_vector : Tuple = light.position - point;
         let distance = shadow_vector.length();
         let direction = shadow_vector.normalize();
 
         let shadow_ray  = Ray::new(point, direction);
-        let intersections = self.intersect_world(shadow_ray);
+        let intersections = self.intersect_visible(shadow_ray, RayKind::Shadow);
+
+        let mut transmission = Color::white();
+        let mut entered: Vec<(Object, f64)> = vec![];
+        for hit in intersections.data.iter().filter(|hit| hit.t > 0. && hit.t < distance) {
+            let material = hit.object.material_ref().at(&hit.object, shadow_ray.position(hit.t));
+            // with caustics the photons carry the light through refractive objects instead
+            if !material.transparent_shadows || material.transparency.equivalent(0.) || (self.caustics.is_some() && material.refracts()) {
+                return Color::black();
+            }
+            match entered.iter().position(|(object, _)| *object == hit.object) {
+                Some(index) => {
+                    let (_, entry) = entered.remove(index);
+                    transmission = transmission * material.transmittance(hit.t - entry);
+                    if let Some(medium) = material.medium {
+                        transmission = transmission * medium.transmittance(hit.t - entry);
+                    }
+                }
+                None => {
+                    // a closed object is crossed twice, its surface only tints the light on the way in
+                    let tint = material.color_at(&hit.object, shadow_ray.position(hit.t));
+                    transmission = transmission * tint * material.transparency;
+                    entered.push((hit.object.clone(), hit.t))
+                }
+            }
+        }
+        for volume in self.volumes(RayKind::Shadow) {
+            transmission = transmission * volume.transmittance(shadow_ray, 0., distance);
+        }
+        match self.medium {
+            Some(medium) => transmission * medium.transmittance(distance),
+            None => transmission
+        }
+    }
 
-        if let Some(hit) = intersections.hit() {
-            if hit.t < distance {
-                return true;
+    pub fn color_at(&self, ray: Ray, remaining: u8) -> Color {
+        self.trace(ray, RayKind::Camera, remaining)
+    }
+
+    pub fn trace(&self, ray: Ray, kind: RayKind, remaining: u8) -> Color {
+        let xs = self.intersect_visible(ray, kind);
+        let (color, t) = match xs.hit() {
+            Some(hit) => {
+                let comps = hit.prepare_computations(ray, &xs);
+                let color = self.shade_hit(&comps, remaining);
+                let distance = comps.t * ray.direction.length();
+                let color = if comps.inside {
+                    // the ray started inside the hit object, so it travelled t through its medium
+                    let color = color * comps.material.transmittance(distance);
+                    match comps.material.medium {
+                        Some(medium) => self.through_medium(&medium, ray, distance, color, Some(&comps.object)),
+                        None => color
+                    }
+                } else {
+                    match self.medium {
+                        Some(medium) => self.through_medium(&medium, ray, distance, color, None),
+                        None => color
+                    }
+                };
+                (color, comps.t)
+            }
+            None => {
+                let color = match self.medium {
+                    Some(medium) => self.through_medium(&medium, ray, medium.max_distance, Color::black(), None),
+                    None => Color::black()
+                };
+                (color, f64::INFINITY)
+            }
+        };
+        self.through_volumes(ray, kind, t, color)
+    }
+
+    fn volumes(&self, kind: RayKind) -> impl Iterator<Item = &Volume> {
+        self.volumes.iter().filter(move |volume| volume.visibility.allows(kind))
+    }
+
+    // volumes in front of t are composited back to front over what the ray sees
+    fn through_volumes(&self, ray: Ray, kind: RayKind, t: f64, background: Color) -> Color {
+        let mut spans: Vec<(&Volume, f64, f64)> = self.volumes(kind)
+            .filter_map(|volume| volume.span(ray).map(|(enter, leave)| (volume, enter.max(0.), leave.min(t))))
+            .filter(|&(_, start, end)| start < end)
+            .collect();
+        spans.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        spans.into_iter().fold(background, |color, (volume, start, end)| self.through_volume(volume, ray, start, end, color))
+    }
+
+    // ray marched, each step is lit by the lights through the volume's own shadow
+    fn through_volume(&self, volume: &Volume, ray: Ray, start: f64, end: f64, background: Color) -> Color {
+        let direction = ray.direction.normalize();
+        let samples = volume.medium.samples.max(1);
+        let step = (end - start) / samples as f64;
+        let step_length = step * ray.direction.length();
+        let mut rng = Rng::from_ray(ray, 13);
+        let offset = rng.next_f64();
+
+        let mut transmittance = Color::white();
+        let mut scattered = Color::black();
+        for i in 0..samples {
+            let point = ray.position(start + (i as f64 + offset) * step);
+            let density = volume.density_at(point);
+            if density <= 0. {
+                continue;
+            }
+            let mut incoming = Color::black();
+            for light in self.lights.iter() {
+                let to_light = (light.position - point).normalize();
+                incoming = incoming + light.intensity * self.shadow_transmission(point, light) * volume.medium.phase.evaluate(to_light.dot(direction));
             }
+            scattered = scattered + transmittance * volume.medium.scattering * incoming * (density * step_length);
+            transmittance = transmittance * volume.medium.transmittance(density * step_length);
         }
-        false
+        background * transmittance + scattered
     }
 
-    pub fn color_at(self, ray: Ray, remaining: u8) -> Color {
-        let xs = self.intersect_world(ray);
-        if xs.hit() != None {
-            let hit = xs.hit().unwrap();
-            let comps = hit.prepare_computations(ray, &xs);
-            self.shade_hit(comps, remaining)
-        } else {
-            Color::black()
+    // attenuates what is seen at the end of the ray and marches along it adding single scattered light,
+    // container is the object whose interior the ray is travelling through, if any
+    fn through_medium(&self, medium: &Medium, ray: Ray, distance: f64, background: Color, container: Option<&Object>) -> Color {
+        let direction = ray.direction.normalize();
+        let length = distance.min(medium.max_distance);
+        let samples = medium.samples.max(1);
+        let step = length / samples as f64;
+        let mut rng = Rng::from_ray(ray, 11);
+        let offset = rng.next_f64();
+
+        let mut scattered = Color::black();
+        for i in 0..samples {
+            let t = (i as f64 + offset) * step;
+            let point = ray.origin + direction * t;
+            let mut incoming = Color::black();
+            for light in self.lights.iter() {
+                let to_light = (light.position - point).normalize();
+                let mut transmission = self.shadow_transmission(point, light);
+                if let Some(container) = container {
+                    // shadow_transmission only attenuates between an entry and an exit, the way out of the container is added here
+                    let exit = container.intersect(Ray::new(point, to_light)).data.into_iter().find(|hit| hit.t > EPSILON);
+                    if let Some(exit) = exit {
+                        transmission = transmission * medium.transmittance(exit.t);
+                    }
+                }
+                incoming = incoming + light.intensity * transmission * medium.phase.evaluate(to_light.dot(direction));
+            }
+            scattered = scattered + medium.scattering * medium.transmittance(t) * incoming * step;
         }
+        background * medium.transmittance(distance) + scattered
     }
 }
 
+pub(crate) fn refract(eye_v: Tuple, normal_v: Tuple, n_ratio: f64) -> Option<Tuple> {
+    let cos_i = eye_v.dot(normal_v);
+    let sin2_t = n_ratio.powi(2) * (1. - cos_i.powi(2));
+    if sin2_t > 1. {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(normal_v * (n_ratio * cos_i - cos_t) - eye_v * n_ratio)
+}
+
 impl Default for World {
     fn default() -> Self {
         World {
             objects: vec![],
             lights: vec![],
+            volumes: vec![],
+            medium: None,
+            ambient_occlusion: None,
+            caustics: None,
         }
     }
 }
 
 #[cfg(test)]
 mod tests_world {
+    use std::f64::consts::PI;
     use crate::assert_equivalent;
+    use crate::conductor::Conductor;
+    use crate::cube::Cube;
+    use crate::cutout::{Cutout, CutoutMask};
     use crate::equivalent::Equivalence;
     use crate::intersection::Intersection;
-    use crate::lights::Light;
-    use crate::materials::Material;
+    use crate::lights::{Light, LinkTarget};
+    use crate::materials::Clearcoat;
     use crate::matrix::Matrix;
-    use crate::patterns::{DefaultPattern, Patterns};
+    use crate::medium::{Medium, PhaseFunction};
+    use crate::object::Visibility;
+    use crate::patterns::{DefaultPattern, Patterns, Stripe};
     use crate::plane::Plane;
     use crate::sphere::Sphere;
+    use crate::volume::DensityGrid;
     use super::*;
 
     fn create_default_world() -> World {
@@ -135,8 +460,8 @@ mod tests_world {
         let mut sphere_2 = Sphere::default();
         sphere_2.set_transform(Matrix::scaling(Tuple::vector(0.5, 0.5, 0.5)));
 
-        let s1 = Object::from(sphere);
-        let s2 = Object::from(sphere_2);
+        let s1 = Object::from(sphere.clone());
+        let s2 = Object::from(sphere_2.clone());
 
         World::new(vec![s1, s2], vec![light])
     }
@@ -164,8 +489,8 @@ mod tests_world {
         let mut sphere_2 = Sphere::default();
         sphere_2.set_transform(Matrix::scaling(Tuple::vector(0.5, 0.5, 0.5)));
 
-        let s1 = Object::from(sphere);
-        let s2 = Object::from(sphere_2);
+        let s1 = Object::from(sphere.clone());
+        let s2 = Object::from(sphere_2.clone());
 
         let world = create_default_world();
 
@@ -196,11 +521,11 @@ mod tests_world {
     fn shading_an_intersection() {
         let w = create_default_world();
         let ray = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
-        let s = w.objects[0];
+        let s = w.objects[0].clone();
         let intersection =  Intersection::new(4., s);
-        let xs = Intersections::new(vec![intersection]);
+        let xs = Intersections::new(vec![intersection.clone()]);
         let comps = intersection.prepare_computations(ray, &xs);
-        let color = w.shade_hit(comps, 4);
+        let color = w.shade_hit(&comps, 4);
 
         assert_equivalent!(color, Color::new(0.38066, 0.47583, 0.2855));
     }
@@ -211,11 +536,11 @@ mod tests_world {
         w.lights[0] = Light::point_light(Tuple::point(0., 0.25, 0.), Color::new(1., 1., 1.));
 
         let ray = Ray::new(Tuple::point(0., 0., 0.), Tuple::vector(0., 0., 1.));
-        let s = w.objects[1];
+        let s = w.objects[1].clone();
         let intersection =  Intersection::new(0.5, s);
-        let xs = Intersections::new(vec![intersection]);
+        let xs = Intersections::new(vec![intersection.clone()]);
         let comps = intersection.prepare_computations(ray, &xs);
-        let color = w.shade_hit(comps, 4);
+        let color = w.shade_hit(&comps, 4);
 
         assert_equivalent!(color, Color::new(0.90498, 0.90498, 0.90498));
     }
@@ -242,18 +567,18 @@ mod tests_world {
     fn the_color_with_an_intersection_behind_the_ray() {
         let mut w = create_default_world();
 
-        let mut outer: Object = w.objects[0];
+        let mut outer: Object = w.objects[0].clone();
         let mut outer_material = outer.material();
         outer_material.ambient = 1.;
         outer.set_material(outer_material);
 
-        let mut inner: Object = w.objects[1];
+        let mut inner: Object = w.objects[1].clone();
         let mut inner_material = inner.material();
         inner_material.ambient = 1.;
         inner.set_material(inner_material);
 
         w.objects[0] = outer;
-        w.objects[1] = inner;
+        w.objects[1] = inner.clone();
 
         let ray = Ray::new(Tuple::point(0., 0., 0.75), Tuple::vector(0., 0., -1.));
 
@@ -266,32 +591,32 @@ mod tests_world {
     fn there_is_no_shadow_when_nothing_is_collinear_with_point_and_light() {
         let world = create_default_world();
         let p = Tuple::point(0., 10., 0.);
-        let light = world.lights[0];
-        assert_eq!(world.is_shadowed(p, light), false);
+        let light = world.lights[0].clone();
+        assert_eq!(world.is_shadowed(p, &light), false);
     }
 
     #[test]
     fn the_shadow_when_an_object_is_between_the_point_and_the_light() {
         let world = create_default_world();
         let p = Tuple::point(10., -10., 10.);
-        let light = world.lights[0];
-        assert!(world.is_shadowed(p, light));
+        let light = world.lights[0].clone();
+        assert!(world.is_shadowed(p, &light));
     }
 
     #[test]
     fn the_is_no_shadow_when_an_object_is_behind_the_light() {
         let world = create_default_world();
         let p = Tuple::point(-20., 20., -20.);
-        let light = world.lights[0];
-        assert_eq!(world.is_shadowed(p, light), false);
+        let light = world.lights[0].clone();
+        assert_eq!(world.is_shadowed(p, &light), false);
     }
 
     #[test]
     fn the_is_no_shadow_when_an_object_is_behind_the_point() {
         let world = create_default_world();
         let p = Tuple::point(-2., 2., -2.);
-        let light = world.lights[0];
-        assert_eq!(world.is_shadowed(p, light), false);
+        let light = world.lights[0].clone();
+        assert_eq!(world.is_shadowed(p, &light), false);
     }
 
     #[test]
@@ -304,14 +629,14 @@ mod tests_world {
 
         let mut s2 = Sphere::default();
         s2.transform = Matrix::translation(Tuple::vector(0., 0., 10.));
-        world.objects.push(Object::from(s2));
+        world.objects.push(Object::from(s2.clone()));
 
         let ray = Ray::new(Tuple::point(0., 0., 5.), Tuple::vector(0., 0., 1.));
 
         let intersection = Intersection::new(4., Object::from(s2));
-        let xs = Intersections::new(vec![intersection]);
+        let xs = Intersections::new(vec![intersection.clone()]);
         let comps = intersection.prepare_computations(ray, &xs);
-        let color = world.shade_hit(comps, 4);
+        let color = world.shade_hit(&comps, 4);
 
         assert_eq!(color, Color::new(0.1, 0.1, 0.1));
     }
@@ -323,12 +648,12 @@ mod tests_world {
 
         let mut shape = Sphere::default();
         shape.material.ambient = 1.;
-        world.objects[1] = Object::from(shape);
+        world.objects[1] = Object::from(shape.clone());
 
         let intersection = Intersection::new(1., Object::from(shape));
-        let xs = Intersections::new(vec![intersection]);
+        let xs = Intersections::new(vec![intersection.clone()]);
         let comps = intersection.prepare_computations(ray, &xs);
-        let color = world.reflected_color(comps, 4);
+        let color = world.reflected_color(&comps, 4);
 
         assert_eq!(color, Color::new(0., 0., 0.));
     }
@@ -340,14 +665,14 @@ mod tests_world {
         let mut plane = Plane::default();
         plane.material.reflective = 0.5;
         plane.set_transform(Matrix::translation(Tuple::vector(0., -1., 0.)));
-        world.objects.push(Object::from(plane));
+        world.objects.push(Object::from(plane.clone()));
 
         let ray = Ray::new(Tuple::point(0., 0., -3.), Tuple::vector(0., -f64::from(2.).sqrt() / 2., f64::from(2.).sqrt() / 2.));
 
-        let intersection = Intersection::new(f64::from(2.).sqrt(), Object::from(plane));
-        let xs = Intersections::new(vec![intersection]);
+        let intersection = Intersection::new(f64::from(2.).sqrt(), Object::from(plane.clone()));
+        let xs = Intersections::new(vec![intersection.clone()]);
         let comps = intersection.prepare_computations(ray, &xs);
-        let color = world.reflected_color(comps, 4);
+        let color = world.reflected_color(&comps, 4);
 
         assert_equivalent!(color, Color::new(0.19033, 0.23791, 0.14274));
     }
@@ -359,14 +684,14 @@ mod tests_world {
         let mut plane = Plane::default();
         plane.material.reflective = 0.5;
         plane.set_transform(Matrix::translation(Tuple::vector(0., -1., 0.)));
-        world.objects.push(Object::from(plane));
+        world.objects.push(Object::from(plane.clone()));
 
         let ray = Ray::new(Tuple::point(0., 0., -3.), Tuple::vector(0., -f64::from(2.).sqrt() / 2., f64::from(2.).sqrt() / 2.));
 
-        let intersection = Intersection::new(f64::from(2.).sqrt(), Object::from(plane));
-        let xs = Intersections::new(vec![intersection]);
+        let intersection = Intersection::new(f64::from(2.).sqrt(), Object::from(plane.clone()));
+        let xs = Intersections::new(vec![intersection.clone()]);
         let comps = intersection.prepare_computations(ray, &xs);
-        let color = world.shade_hit(comps, 4);
+        let color = world.shade_hit(&comps, 4);
 
         assert_equivalent!(color, Color::new(0.87675, 0.92434, 0.82917));
     }
@@ -379,12 +704,12 @@ mod tests_world {
         let mut lower = Plane::default();
         lower.material.reflective = 1.;
         lower.set_transform(Matrix::translation(Tuple::vector(0., -1., 0.)));
-        world.objects.push(Object::from(lower));
+        world.objects.push(Object::from(lower.clone()));
 
         let mut upper = Plane::default();
         upper.material.reflective = 1.;
         upper.set_transform(Matrix::translation(Tuple::vector(0., 1., 0.)));
-        world.objects.push(Object::from(upper));
+        world.objects.push(Object::from(upper.clone()));
 
         let ray = Ray::new(Tuple::point(0., 0., 0.), Tuple::vector(0., 1., 0.));
 
@@ -399,14 +724,14 @@ mod tests_world {
         let mut plane = Plane::default();
         plane.material.reflective = 0.5;
         plane.set_transform(Matrix::translation(Tuple::vector(0., -1., 0.)));
-        world.objects.push(Object::from(plane));
+        world.objects.push(Object::from(plane.clone()));
 
         let ray = Ray::new(Tuple::point(0., 0., -3.), Tuple::vector(0., -f64::from(2.).sqrt() / 2., f64::from(2.).sqrt() / 2.));
 
-        let intersection = Intersection::new(f64::from(2.).sqrt(), Object::from(plane));
-        let xs = Intersections::new(vec![intersection]);
+        let intersection = Intersection::new(f64::from(2.).sqrt(), Object::from(plane.clone()));
+        let xs = Intersections::new(vec![intersection.clone()]);
         let comps = intersection.prepare_computations(ray, &xs);
-        let color = world.reflected_color(comps, 0);
+        let color = world.reflected_color(&comps, 0);
 
         assert_equivalent!(color, Color::black());
     }
@@ -414,15 +739,15 @@ mod tests_world {
     #[test]
     fn the_refracted_color_with_an_opaque_surface() {
         let world = create_default_world();
-        let shape = world.objects[0];
+        let shape = world.objects[0].clone();
 
         let ray = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
 
-        let intersect1 = Intersection::new(4.,  shape);
+        let intersect1 = Intersection::new(4.,  shape.clone());
         let intersect2 = Intersection::new(6.,  shape);
         let xs = Intersections::new(vec![intersect1, intersect2]);
         let comp = xs.data[0].prepare_computations(ray, &xs);
-        let color = world.refracted_color(comp, 5);
+        let color = world.refracted_color(&comp, 5);
 
         assert_equivalent!(color, Color::black());
     }
@@ -430,7 +755,7 @@ mod tests_world {
     #[test]
     fn the_refracted_color_at_the_maximum_recursive_depth() {
         let world = create_default_world();
-        let mut shape: Object = world.objects[0];
+        let mut shape: Object = world.objects[0].clone();
         let mut material = Material::phong();
         material.transparency = 1.0;
         material.reflactive_index = 1.5;
@@ -438,11 +763,11 @@ mod tests_world {
 
         let ray = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
 
-        let intersect1 = Intersection::new(4.,  shape);
+        let intersect1 = Intersection::new(4.,  shape.clone());
         let intersect2 = Intersection::new(6.,  shape);
         let xs = Intersections::new(vec![intersect1, intersect2]);
         let comp = xs.data[0].prepare_computations(ray, &xs);
-        let color = world.refracted_color(comp, 0);
+        let color = world.refracted_color(&comp, 0);
 
         assert_equivalent!(color, Color::black());
     }
@@ -450,7 +775,7 @@ mod tests_world {
     #[test]
     fn the_refracted_color_under_total_internal_reflection() {
         let world = create_default_world();
-        let mut shape: Object = world.objects[0];
+        let mut shape: Object = world.objects[0].clone();
         let mut material = Material::phong();
         material.transparency = 1.0;
         material.reflactive_index = 1.5;
@@ -458,12 +783,12 @@ mod tests_world {
 
         let ray = Ray::new(Tuple::point(0., 0., f64::from(2.).sqrt() / 2.), Tuple::vector(0., 1., 0.));
 
-        let intersect1 = Intersection::new(-f64::from(2.).sqrt() / 2.,  shape);
+        let intersect1 = Intersection::new(-f64::from(2.).sqrt() / 2.,  shape.clone());
         let intersect2 = Intersection::new(f64::from(2.).sqrt() / 2.,  shape);
         let xs = Intersections::new(vec![intersect1, intersect2]);
 
         let comp = xs.data[1].prepare_computations(ray, &xs);
-        let color = world.refracted_color(comp, 5);
+        let color = world.refracted_color(&comp, 5);
 
         assert_equivalent!(color, Color::black());
     }
@@ -471,13 +796,13 @@ mod tests_world {
     #[test]
     fn the_refracted_color_with_a_refracted_ray() {
         let world = create_default_world();
-        let mut a: Object = world.objects[0];
+        let mut a: Object = world.objects[0].clone();
         let mut material_a = a.material();
         material_a.ambient = 1.;
         material_a.pattern = Option::from(Patterns::from(DefaultPattern::default()));
         a.set_material(material_a);
 
-        let mut b: Object = world.objects[0];
+        let mut b: Object = world.objects[0].clone();
         let mut material_b = b.material();
         material_b.transparency = 1.;
         material_b.reflactive_index = 1.5;
@@ -485,15 +810,15 @@ mod tests_world {
 
         let ray = Ray::new(Tuple::point(0., 0., 0.1), Tuple::vector(0., 1., 0.));
 
-        let intersect1 = Intersection::new(-0.9899,  Object::from(a));
-        let intersect2 = Intersection::new(-0.4899,  Object::from(b));
-        let intersect3 = Intersection::new(0.4899,  Object::from(b));
-        let intersect4 = Intersection::new(0.9899,  Object::from(a));
+        let intersect1 = Intersection::new(-0.9899,  Object::from(a.clone()));
+        let intersect2 = Intersection::new(-0.4899,  Object::from(b.clone()));
+        let intersect3 = Intersection::new(0.4899,  Object::from(b.clone()));
+        let intersect4 = Intersection::new(0.9899,  Object::from(a.clone()));
 
         let xs = Intersections::new(vec![intersect1, intersect2, intersect3, intersect4]);
         let comp = xs.data[2].prepare_computations(ray, &xs);
 
-        let color = world.refracted_color(comp, 5);
+        let color = world.refracted_color(&comp, 5);
         assert_equivalent!(color, Color::new(0.08, 0.1, 0.06));
     }
 
@@ -505,22 +830,22 @@ mod tests_world {
         floor.material.transparency = 0.5;
         floor.material.reflactive_index = 1.5;
 
-        world.objects.push(Object::from(floor));
+        world.objects.push(Object::from(floor.clone()));
 
         let mut ball = Sphere::default();
         ball.material.color = Color::new(1., 0., 0.);
         ball.material.ambient = 0.5;
         ball.set_transform(Matrix::translation(Tuple::vector(0., -3.5, -0.5)));
 
-        world.objects.push(Object::from(ball));
+        world.objects.push(Object::from(ball.clone()));
 
         let ray = Ray::new(Tuple::point(0., 0., -3.), Tuple::vector(0., -f64::from(2.).sqrt() / 2., f64::from(2.).sqrt() / 2.));
 
-        let intersect = Intersection::new(f64::from(2.).sqrt(),  Object::from(floor));
+        let intersect = Intersection::new(f64::from(2.).sqrt(),  Object::from(floor.clone()));
         let xs = Intersections::new(vec![intersect]);
 
         let comp = xs.data[0].prepare_computations(ray, &xs);
-        let color = world.shade_hit(comp, 5);
+        let color = world.shade_hit(&comp, 5);
         assert_equivalent!(color, Color::new(0.93642, 0.68642, 0.68642));
     }
 
@@ -533,22 +858,561 @@ mod tests_world {
         floor.material.transparency = 0.5;
         floor.material.reflactive_index = 1.5;
 
-        world.objects.push(Object::from(floor));
+        world.objects.push(Object::from(floor.clone()));
 
         let mut ball = Sphere::default();
         ball.material.color = Color::new(1., 0., 0.);
         ball.material.ambient = 0.5;
         ball.set_transform(Matrix::translation(Tuple::vector(0., -3.5, -0.5)));
 
-        world.objects.push(Object::from(ball));
+        world.objects.push(Object::from(ball.clone()));
 
         let ray = Ray::new(Tuple::point(0., 0., -3.), Tuple::vector(0., -f64::from(2.).sqrt() / 2., f64::from(2.).sqrt() / 2.));
 
-        let intersect = Intersection::new(f64::from(2.).sqrt(),  Object::from(floor));
+        let intersect = Intersection::new(f64::from(2.).sqrt(),  Object::from(floor.clone()));
         let xs = Intersections::new(vec![intersect]);
 
         let comp = xs.data[0].prepare_computations(ray, &xs);
-        let color = world.shade_hit(comp, 5);
+        let color = world.shade_hit(&comp, 5);
         assert_equivalent!(color, Color::new(0.93391, 0.69643, 0.69243));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn a_rough_reflective_material_blurs_the_reflection() {
+        let mut world = create_default_world();
+
+        let mut plane = Plane::default();
+        plane.material.reflective = 0.5;
+        plane.material.roughness = 0.4;
+        plane.set_transform(Matrix::translation(Tuple::vector(0., -1., 0.)));
+        world.objects.push(Object::from(plane.clone()));
+
+        let ray = Ray::new(Tuple::point(0., 0., -3.), Tuple::vector(0., -2f64.sqrt() / 2., 2f64.sqrt() / 2.));
+
+        let intersection = Intersection::new(2f64.sqrt(), Object::from(plane.clone()));
+        let xs = Intersections::new(vec![intersection.clone()]);
+        let comps = intersection.prepare_computations(ray, &xs);
+        let glossy = world.reflected_color(&comps, 4);
+
+        assert_equivalent!(glossy, world.reflected_color(&comps, 4));
+        assert!(!glossy.equivalent(Color::new(0.19033, 0.23791, 0.14274)));
+        assert!(glossy.green > 0. && glossy.green < 0.5);
+    }
+
+    #[test]
+    fn a_single_glossy_sample_traces_the_perfect_ray() {
+        let mut world = create_default_world();
+
+        let mut plane = Plane::default();
+        plane.material.reflective = 0.5;
+        plane.material.roughness = 0.4;
+        plane.material.glossy_samples = 1;
+        plane.set_transform(Matrix::translation(Tuple::vector(0., -1., 0.)));
+        world.objects.push(Object::from(plane.clone()));
+
+        let ray = Ray::new(Tuple::point(0., 0., -3.), Tuple::vector(0., -2f64.sqrt() / 2., 2f64.sqrt() / 2.));
+
+        let intersection = Intersection::new(2f64.sqrt(), Object::from(plane.clone()));
+        let xs = Intersections::new(vec![intersection.clone()]);
+        let comps = intersection.prepare_computations(ray, &xs);
+
+        assert_equivalent!(world.reflected_color(&comps, 4), Color::new(0.19033, 0.23791, 0.14274));
+    }
+
+    #[test]
+    fn rough_mirrors_facing_each_other_fan_out_only_once() {
+        let rough = Material { reflective: 1., roughness: 0.5, ..Material::phong() };
+        let mut floor = Plane { material: rough.clone(), ..Plane::default() };
+        floor.set_transform(Matrix::translation(Tuple::vector(0., -1., 0.)));
+        let mut ceiling = Plane { material: rough, ..Plane::default() };
+        ceiling.set_transform(Matrix::translation(Tuple::vector(0., 1., 0.)));
+        let world = World::new(vec![Object::from(floor), Object::from(ceiling)], vec![Light::point_light(Tuple::point(0., 0., 0.), Color::white())]);
+        let ray = Ray::new(Tuple::point(0., 0., 0.), Tuple::vector(0., -1., 1.).normalize());
+
+        let color = world.color_at(ray, 8);
+
+        assert!(color.red > 0. && color.red.is_finite());
+    }
+
+    #[test]
+    fn frosted_glass_blurs_what_is_behind_it() {
+        let mut floor = Plane::default();
+        floor.material.ambient = 1.;
+        floor.material.diffuse = 0.;
+        floor.material.specular = 0.;
+        floor.material.pattern = Some(Patterns::from(Stripe::new(Color::white(), Color::black())));
+        floor.set_transform(Matrix::translation(Tuple::vector(0., -1., 0.)));
+        let world = World::new(vec![Object::from(floor)], vec![Light::point_light(Tuple::point(0., 10., 0.), Color::white())]);
+
+        let mut glass = Plane::default();
+        glass.material.transparency = 1.;
+        glass.material.reflactive_index = 1.5;
+        let ray = Ray::new(Tuple::point(0.05, 1., 0.), Tuple::vector(0., -1., 0.));
+
+        let clear = Intersection::new(1., Object::from(glass.clone()));
+        let comps = clear.prepare_computations(ray, &Intersections::new(vec![clear.clone()]));
+        assert_equivalent!(world.refracted_color(&comps, 5), Color::white());
+
+        glass.material.roughness = 0.5;
+        let frosted = Intersection::new(1., Object::from(glass));
+        let comps = frosted.prepare_computations(ray, &Intersections::new(vec![frosted.clone()]));
+        let color = world.refracted_color(&comps, 5);
+        assert!(color.red > 0.2 && color.red < 1.);
+    }
+
+    #[test]
+    fn light_through_thick_tinted_glass_is_darker_than_through_thin() {
+        let glass_color = |thickness: f64| {
+            let mut floor = Plane::default();
+            floor.material.ambient = 1.;
+            floor.material.diffuse = 0.;
+            floor.material.specular = 0.;
+            floor.set_transform(Matrix::translation(Tuple::vector(0., -5., 0.)));
+
+            let mut material = Material::glass();
+            material.reflective = 0.;
+            material.ambient = 0.;
+            material.diffuse = 0.;
+            material.specular = 0.;
+            material.absorption_color = Color::new(0.2, 0.8, 0.2);
+            material.absorption_density = 1.;
+
+            let mut slab = Cube::default();
+            slab.set_material(material);
+            slab.set_transform(Matrix::scaling(Tuple::vector(10., thickness / 2., 10.)));
+
+            let world = World::new(
+                vec![Object::from(floor), Object::from(slab)],
+                vec![Light::point_light(Tuple::point(0., 10., 0.), Color::white())]
+            );
+            let ray = Ray::new(Tuple::point(0., 3., 0.), Tuple::vector(0., -1., 0.));
+            world.color_at(ray, 5)
+        };
+
+        let thin = glass_color(0.5);
+        let thick = glass_color(2.);
+
+        assert_equivalent!(thin, Color::new((-0.4f64).exp(), (-0.1f64).exp(), (-0.4f64).exp()));
+        assert_equivalent!(thick, Color::new((-1.6f64).exp(), (-0.4f64).exp(), (-1.6f64).exp()));
+    }
+
+    #[test]
+    fn glass_casts_a_lighter_shadow() {
+        let light = Light::point_light(Tuple::point(0., 10., 0.), Color::white());
+        let mut glass = Sphere::grass(1.);
+        glass.material.transparency = 0.8;
+        let world = World::new(vec![Object::from(glass)], vec![light.clone()]);
+        let p = Tuple::point(0., -5., 0.);
+
+        assert_equivalent!(world.shadow_transmission(p, &light), Color::new(0.8, 0.8, 0.8));
+        assert!(!world.is_shadowed(p, &light));
+    }
+
+    #[test]
+    fn coloured_glass_tints_its_shadow() {
+        let light = Light::point_light(Tuple::point(0., 10., 0.), Color::white());
+        let mut glass = Sphere::grass(1.);
+        glass.material.color = Color::new(1., 0.5, 0.);
+        let world = World::new(vec![Object::from(glass)], vec![light.clone()]);
+
+        assert_equivalent!(world.shadow_transmission(Tuple::point(0., -5., 0.), &light), Color::new(1., 0.5, 0.));
+    }
+
+    #[test]
+    fn absorbing_glass_darkens_its_shadow_with_thickness() {
+        let light = Light::point_light(Tuple::point(0., 10., 0.), Color::white());
+        let mut glass = Sphere::grass(1.);
+        glass.material.absorption_color = Color::new(1., 1., 0.);
+        glass.material.absorption_density = 0.5;
+        let world = World::new(vec![Object::from(glass)], vec![light.clone()]);
+
+        assert_equivalent!(world.shadow_transmission(Tuple::point(0., -5., 0.), &light), Color::new(1., 1., (-1f64).exp()));
+    }
+
+    #[test]
+    fn transparent_shadows_can_be_turned_off() {
+        let light = Light::point_light(Tuple::point(0., 10., 0.), Color::white());
+        let glass = Sphere::grass(1.);
+        let transparent = World::new(vec![Object::from(glass.clone())], vec![light.clone()]);
+        let mut opaque = glass;
+        opaque.material.transparent_shadows = false;
+        let opaque = World::new(vec![Object::from(opaque)], vec![light.clone()]);
+
+        assert!(!transparent.is_shadowed(Tuple::point(0., -5., 0.), &light));
+        assert!(opaque.is_shadowed(Tuple::point(0., -5., 0.), &light));
+    }
+
+    #[test]
+    fn an_object_hidden_from_the_camera_still_shows_in_reflections() {
+        let mut world = create_default_world();
+        for object in world.objects.iter_mut() {
+            object.set_visibility(Visibility { camera: false, ..Visibility::default() });
+        }
+        let ray = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+
+        assert_equivalent!(world.color_at(ray, 4), Color::black());
+        assert_equivalent!(world.trace(ray, RayKind::Reflection, 4), Color::new(0.38066, 0.47583, 0.2855));
+    }
+
+    #[test]
+    fn an_object_can_be_hidden_from_reflections_and_refractions() {
+        let mut world = create_default_world();
+        let mut plane = Plane::default();
+        plane.material.reflective = 0.5;
+        plane.set_transform(Matrix::translation(Tuple::vector(0., -1., 0.)));
+        world.objects.push(Object::from(plane.clone()));
+        for object in world.objects.iter_mut().take(2) {
+            object.set_visibility(Visibility { reflection: false, refraction: false, ..Visibility::default() });
+        }
+
+        let ray = Ray::new(Tuple::point(0., 0., -3.), Tuple::vector(0., -2f64.sqrt() / 2., 2f64.sqrt() / 2.));
+        let intersection = Intersection::new(2f64.sqrt(), Object::from(plane));
+        let xs = Intersections::new(vec![intersection.clone()]);
+        let comps = intersection.prepare_computations(ray, &xs);
+
+        assert_equivalent!(world.reflected_color(&comps, 4), Color::black());
+        assert_equivalent!(world.trace(Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.)), RayKind::Refraction, 4), Color::black());
+    }
+
+    #[test]
+    fn an_object_that_casts_no_shadow() {
+        let mut world = create_default_world();
+        let p = Tuple::point(10., -10., 10.);
+        let light = world.lights[0].clone();
+        assert!(world.is_shadowed(p, &light));
+
+        for object in world.objects.iter_mut() {
+            object.set_visibility(Visibility { shadow: false, ..Visibility::default() });
+        }
+
+        assert!(!world.is_shadowed(p, &light));
+    }
+
+    #[test]
+    fn shade_hit_only_uses_lights_linked_to_the_object() {
+        let mut world = create_default_world();
+        world.objects[0].set_name("hero");
+        let ray = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+        let intersection = Intersection { object_id: Some(0), ..Intersection::new(4., world.objects[0].clone()) };
+        let comps = intersection.prepare_computations(ray, &Intersections::new(vec![intersection.clone()]));
+        let lit = world.shade_hit(&comps, 4);
+
+        world.lights[0] = world.lights[0].clone().excluding(vec![LinkTarget::from("hero")]);
+        assert_equivalent!(world.shade_hit(&comps, 4), Color::black());
+
+        world.lights[0] = world.lights[0].clone().including(vec![LinkTarget::from(0)]);
+        assert_equivalent!(world.shade_hit(&comps, 4), lit);
+
+        world.lights[0] = world.lights[0].clone().including(vec![LinkTarget::from(1)]);
+        assert_equivalent!(world.shade_hit(&comps, 4), Color::black());
+    }
+
+    #[test]
+    fn identical_objects_are_linked_by_their_own_ids() {
+        let twin = Sphere { material: Material { ambient: 0., specular: 0., ..Material::phong() }, ..Sphere::default() };
+        let light = Light::point_light(Tuple::point(0., 0., -10.), Color::white()).including(vec![LinkTarget::from(1)]);
+        let world = World::new(vec![Object::from(twin.clone()), Object::from(twin)], vec![light]);
+        let ray = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+
+        let xs = world.intersect_world(ray);
+        let first = xs.data.iter().find(|hit| hit.object_id == Some(0)).unwrap();
+        let second = xs.data.iter().find(|hit| hit.object_id == Some(1)).unwrap();
+
+        assert_equivalent!(world.shade_hit(&first.prepare_computations(ray, &xs), 4), Color::black());
+        assert_equivalent!(world.shade_hit(&second.prepare_computations(ray, &xs), 4), Color::new(0.9, 0.9, 0.9));
+    }
+
+    #[test]
+    fn a_gold_mirror_tints_what_it_reflects() {
+        let mut world = create_default_world();
+
+        let mut plane = Plane::default();
+        plane.set_material(Material::metal(Conductor::gold()));
+        plane.material.ambient = 0.;
+        plane.set_transform(Matrix::translation(Tuple::vector(0., -1., 0.)));
+        world.objects.push(Object::from(plane.clone()));
+        world.lights.clear();
+        world.lights.push(Light::point_light(Tuple::point(-10.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0)).excluding(vec![LinkTarget::from(2)]));
+
+        let ray = Ray::new(Tuple::point(0., 0., -3.), Tuple::vector(0., -2f64.sqrt() / 2., 2f64.sqrt() / 2.));
+        let intersection = Intersection { object_id: Some(2), ..Intersection::new(2f64.sqrt(), Object::from(plane.clone())) };
+        let xs = Intersections::new(vec![intersection.clone()]);
+        let comps = intersection.prepare_computations(ray, &xs);
+
+        let reflectance = Conductor::gold().reflectance(2f64.sqrt() / 2.);
+        let mirrored = Color::new(0.38066, 0.47583, 0.2855) * reflectance;
+        assert_equivalent!(world.shade_hit(&comps, 4), mirrored);
+    }
+
+    #[test]
+    fn a_clearcoat_reflects_the_world_over_a_matte_base() {
+        let mut world = create_default_world();
+
+        let mut plane = Plane::default();
+        plane.material.clearcoat = Some(Clearcoat::new(1.5, 0.));
+        plane.set_transform(Matrix::translation(Tuple::vector(0., -1., 0.)));
+        world.objects.push(Object::from(plane.clone()));
+
+        let ray = Ray::new(Tuple::point(0., 0., -3.), Tuple::vector(0., -2f64.sqrt() / 2., 2f64.sqrt() / 2.));
+        let intersection = Intersection::new(2f64.sqrt(), Object::from(plane.clone()));
+        let xs = Intersections::new(vec![intersection.clone()]);
+        let comps = intersection.prepare_computations(ray, &xs);
+
+        plane.material.clearcoat = None;
+        let bare = Intersection::new(2f64.sqrt(), Object::from(plane));
+        let bare_comps = bare.prepare_computations(ray, &Intersections::new(vec![bare.clone()]));
+
+        let reflectance = Clearcoat::new(1.5, 0.).fresnel(2f64.sqrt() / 2.);
+        let base = world.shade_hit(&bare_comps, 4);
+        let coated = world.shade_hit(&comps, 4);
+        assert!(coated.green > base.green * (1. - reflectance));
+        assert_equivalent!(coated - base * (1. - reflectance), Color::new(0.38066, 0.47583, 0.2855) * reflectance);
+    }
+
+    #[test]
+    fn camera_and_shadow_rays_pass_through_a_cutout() {
+        let mut sphere = Sphere::default();
+        let mask = Patterns::from(Stripe::new(Color::black(), Color::white()));
+        sphere.material.cutout = Some(Cutout::new(CutoutMask::from(mask)));
+        let world = World::new(vec![Object::from(sphere)], vec![]);
+
+        assert_equivalent!(world.color_at(Ray::new(Tuple::point(0.5, 0., -5.), Tuple::vector(0., 0., 1.)), 4), Color::black());
+        assert_eq!(world.intersect_world(Ray::new(Tuple::point(-0.5, 0., -5.), Tuple::vector(0., 0., 1.))).data.len(), 2);
+
+        let through = Light::point_light(Tuple::point(0.5, 10., 0.), Color::white());
+        let blocked = Light::point_light(Tuple::point(-0.5, 10., 0.), Color::white());
+        assert!(!world.is_shadowed(Tuple::point(0.5, -5., 0.), &through));
+        assert!(world.is_shadowed(Tuple::point(-0.5, -5., 0.), &blocked));
+    }
+
+    #[test]
+    fn a_subsurface_sphere_glows_when_lit_from_behind() {
+        let light = Light::point_light(Tuple::point(0., 0., 10.), Color::white());
+        let ray = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+        let shade = |subsurface: Option<Subsurface>| {
+            let mut sphere = Sphere::default();
+            sphere.material.subsurface = subsurface;
+            let world = World::new(vec![Object::from(sphere)], vec![light.clone()]);
+            world.color_at(ray, 4)
+        };
+
+        let opaque = shade(None);
+        let translucent = shade(Some(Subsurface::new(Color::new(0.9, 0.5, 0.3), 2.)));
+
+        assert_equivalent!(opaque, Color::new(0.1, 0.1, 0.1));
+        assert!(translucent.red > 0.2);
+        assert!(translucent.red > translucent.green && translucent.green > translucent.blue);
+    }
+
+    #[test]
+    fn a_short_mean_free_path_lets_less_light_through() {
+        let light = Light::point_light(Tuple::point(0., 0., 10.), Color::white());
+        let ray = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+        let shade = |mean_free_path: f64| {
+            let mut sphere = Sphere::default();
+            sphere.material.subsurface = Some(Subsurface::new(Color::new(0.8, 0.8, 0.8), mean_free_path));
+            let world = World::new(vec![Object::from(sphere)], vec![light.clone()]);
+            world.color_at(ray, 4)
+        };
+
+        assert!(shade(0.1).red < shade(2.).red);
+    }
+
+    fn glowing(material: Material) -> Material {
+        Material { ambient: 1., diffuse: 0., specular: 0., ..material }
+    }
+
+    #[test]
+    fn an_absorbing_atmosphere_dims_distant_surfaces() {
+        let mut sphere = Sphere::default();
+        sphere.set_material(glowing(Material::phong()));
+        let light = Light::point_light(Tuple::point(-10., 10., -10.), Color::white());
+        let world = World::new(vec![Object::from(sphere)], vec![light])
+            .with_medium(Medium::new(Color::new(0.1, 0.2, 0.3), Color::black()));
+
+        let color = world.color_at(Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.)), 4);
+
+        assert_equivalent!(color, Color::new((-0.4f64).exp(), (-0.8f64).exp(), (-1.2f64).exp()));
+    }
+
+    #[test]
+    fn fog_scatters_light_towards_the_eye_where_the_light_reaches() {
+        let light = Light::point_light(Tuple::point(0., 5., 0.), Color::white());
+        let fog = Medium::fog(0.05).with_max_distance(20.).with_samples(64);
+        let ray = Ray::new(Tuple::point(0., 0., -10.), Tuple::vector(0., 0., 1.));
+        let blocker = |width: f64| {
+            let mut cube = Cube::default();
+            cube.set_transform(Matrix::translation(Tuple::vector(0., 2.5, 0.)) * Matrix::scaling(Tuple::vector(width, 0.1, width)));
+            Object::from(cube)
+        };
+
+        let open = World::new(vec![], vec![light.clone()]).with_medium(fog).color_at(ray, 4);
+        let gaps = World::new(vec![blocker(3.)], vec![light.clone()]).with_medium(fog).color_at(ray, 4);
+        let closed = World::new(vec![blocker(100.)], vec![light]).with_medium(fog).color_at(ray, 4);
+
+        assert!(open.red > 0.01);
+        assert!(gaps.red > 0. && gaps.red < open.red);
+        assert_equivalent!(closed, Color::black());
+    }
+
+    #[test]
+    fn forward_scattering_brightens_the_fog_looking_towards_the_light() {
+        let light = Light::point_light(Tuple::point(0., 0., 30.), Color::white());
+        let ray = Ray::new(Tuple::point(0., 0., -10.), Tuple::vector(0., 0., 1.));
+        let fog = Medium::fog(0.05).with_max_distance(20.);
+
+        let isotropic = World::new(vec![], vec![light.clone()]).with_medium(fog).color_at(ray, 4);
+        let forward = World::new(vec![], vec![light]).with_medium(fog.with_phase(PhaseFunction::HenyeyGreenstein(0.7))).color_at(ray, 4);
+
+        assert!(forward.red > isotropic.red);
+    }
+
+    #[test]
+    fn a_volume_inside_an_object_absorbs_the_rays_passing_through() {
+        let light = Light::point_light(Tuple::point(0., 0., -10.), Color::white());
+        let mut backdrop = Plane::default();
+        backdrop.set_material(glowing(Material::phong()));
+        backdrop.set_transform(Matrix::translation(Tuple::vector(0., 0., 5.)) * Matrix::rotation_x(PI / 2.));
+        let mut volume = Sphere::default();
+        volume.set_material(Material { reflective: 0., reflactive_index: 1., ambient: 0., diffuse: 0., specular: 0., ..Material::glass() });
+        volume.material.medium = Some(Medium::new(Color::new(0.5, 0.5, 0.5), Color::black()));
+        let ray = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+
+        let world = World::new(vec![Object::from(backdrop), Object::from(volume)], vec![light]);
+
+        assert_equivalent!(world.color_at(ray, 5), Color::white() * (-1f64).exp());
+    }
+
+    #[test]
+    fn smoke_dims_what_is_behind_it() {
+        let light = Light::point_light(Tuple::point(0., 0., -10.), Color::white());
+        let mut backdrop = Plane::default();
+        backdrop.set_material(glowing(Material::phong()));
+        backdrop.set_transform(Matrix::translation(Tuple::vector(0., 0., 5.)) * Matrix::rotation_x(PI / 2.));
+        let smoke = Volume::new(DensityGrid::from_text(b"1 1 1 1").unwrap(), Medium::new(Color::new(0.5, 0.5, 0.5), Color::black()));
+        let ray = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+
+        let world = World::new(vec![Object::from(backdrop)], vec![light]).with_volume(smoke);
+
+        assert_equivalent!(world.color_at(ray, 4), Color::white() * (-1f64).exp());
+    }
+
+    #[test]
+    fn a_cloud_scatters_light_only_where_it_has_density() {
+        let light = Light::point_light(Tuple::point(0., 10., 0.), Color::white());
+        let cloud = Volume::new(DensityGrid::from_text(b"2 1 1  0 1").unwrap(), Medium::fog(1.));
+        let world = World::new(vec![], vec![light]).with_volume(cloud);
+
+        let empty = world.color_at(Ray::new(Tuple::point(-0.9, 0., -5.), Tuple::vector(0., 0., 1.)), 4);
+        let dense = world.color_at(Ray::new(Tuple::point(0.9, 0., -5.), Tuple::vector(0., 0., 1.)), 4);
+
+        assert_equivalent!(empty, Color::black());
+        assert!(dense.red > 0.01);
+    }
+
+    #[test]
+    fn a_volume_casts_a_soft_shadow() {
+        let light = Light::point_light(Tuple::point(0., 10., 0.), Color::white());
+        let cloud = Volume::new(DensityGrid::from_noise(8, 2., 2, 7), Medium::fog(2.));
+        let world = World::new(vec![], vec![light.clone()]).with_volume(cloud);
+
+        let transmission = world.shadow_transmission(Tuple::point(0., -5., 0.), &light);
+
+        assert!(transmission.red > 0. && transmission.red < 1.);
+        assert_equivalent!(world.shadow_transmission(Tuple::point(5., -5., 0.), &Light::point_light(Tuple::point(5., 10., 0.), Color::white())), Color::white());
+    }
+
+    fn floor_under_a_ceiling() -> World {
+        let floor = Plane::default();
+        let mut ceiling = Plane::default();
+        ceiling.set_transform(Matrix::translation(Tuple::vector(0., 1., 0.)));
+        ceiling.set_visibility(Visibility { camera: false, ..Visibility::default() });
+        let light = Light::point_light(Tuple::point(0., 10., 0.), Color::white());
+        World::new(vec![Object::from(floor), Object::from(ceiling)], vec![light])
+    }
+
+    #[test]
+    fn occlusion_counts_the_hemisphere_rays_blocked_nearby() {
+        let world = floor_under_a_ceiling();
+        let point = Tuple::point(0., 0.00001, 0.);
+        let normal = Tuple::vector(0., 1., 0.);
+
+        assert_equivalent!(world.occlusion_at(point, normal, &AmbientOcclusion::new(32, 100.)), 0.);
+        assert_equivalent!(world.occlusion_at(point, normal, &AmbientOcclusion::new(32, 0.5)), 1.);
+        assert_equivalent!(World::new(vec![Object::from(Plane::default())], vec![]).occlusion_at(point, normal, &AmbientOcclusion::default()), 1.);
+    }
+
+    #[test]
+    fn a_corner_is_partly_occluded() {
+        let mut wall = Plane::default();
+        wall.set_transform(Matrix::rotation_z(PI / 2.));
+        let world = World::new(vec![Object::from(Plane::default()), Object::from(wall)], vec![]);
+
+        let occlusion = world.occlusion_at(Tuple::point(0.1, 0.00001, 0.), Tuple::vector(0., 1., 0.), &AmbientOcclusion::new(256, 10.));
+
+        assert!(occlusion > 0.3 && occlusion < 0.9);
+    }
+
+    #[test]
+    fn ambient_occlusion_darkens_the_ambient_term() {
+        let world = floor_under_a_ceiling();
+        let ray = Ray::new(Tuple::point(0., 0.5, 0.), Tuple::vector(0., -1., 0.));
+
+        assert_equivalent!(world.color_at(ray, 4), Color::new(0.1, 0.1, 0.1));
+        assert_equivalent!(world.with_ambient_occlusion(AmbientOcclusion::new(16, 100.)).color_at(ray, 4), Color::black());
+    }
+
+    fn glass_ball_over_a_floor() -> World {
+        let mut glass = Sphere::grass(1.);
+        glass.set_transform(Matrix::translation(Tuple::vector(0., 2., 0.)));
+        let light = Light::point_light(Tuple::point(0., 10., 0.), Color::white());
+        World::new(vec![Object::from(Plane::default()), Object::from(glass)], vec![light])
+    }
+
+    #[test]
+    fn a_glass_ball_focuses_photons_onto_the_floor() {
+        let world = glass_ball_over_a_floor().with_caustics(20000, 0.3);
+        let caustics = world.caustics.as_ref().unwrap();
+        let up = Tuple::vector(0., 1., 0.);
+
+        let focus = caustics.irradiance(Tuple::point(0., 0., 0.), up);
+        let aside = caustics.irradiance(Tuple::point(3., 0., 0.), up);
+
+        assert!(!caustics.tree.is_empty());
+        assert!(focus.red > 1.);
+        assert_equivalent!(aside, Color::black());
+    }
+
+    #[test]
+    fn caustics_replace_the_transparent_shadow_under_glass() {
+        let world = glass_ball_over_a_floor();
+        let ray = Ray::new(Tuple::point(0., 0.5, -0.5), Tuple::vector(0., -1., 1.).normalize());
+        let plain = world.color_at(ray, 4);
+
+        let world = world.with_caustics(20000, 0.3);
+        assert!(world.is_shadowed(Tuple::point(0., 0.00001, 0.), &world.lights[0]));
+        assert!(world.color_at(ray, 4).red > plain.red);
+    }
+
+    #[test]
+    fn caustics_leave_the_shadows_of_flat_panes_transparent() {
+        let tinted = Material { color: Color::new(1., 0.5, 0.), transparency: 1., transparent_shadows: true, ..Material::phong() };
+        let mut pane = Plane { material: tinted, ..Plane::default() };
+        pane.set_transform(Matrix::translation(Tuple::vector(0., 5., 0.)));
+        let mut world = glass_ball_over_a_floor();
+        world.objects.push(Object::from(pane));
+        let world = world.with_caustics(1000, 0.3);
+
+        assert_equivalent!(world.shadow_transmission(Tuple::point(3., 0.00001, 0.), &world.lights[0]), Color::new(1., 0.5, 0.));
+        assert!(world.is_shadowed(Tuple::point(0., 0.00001, 0.), &world.lights[0]));
+    }
+
+    #[test]
+    fn an_emissive_surface_glows_without_light() {
+        let mut sphere = Sphere::default();
+        sphere.material.emission = Color::new(0.5, 0.2, 0.1);
+        let world = World::new(vec![Object::from(sphere)], vec![]);
+
+        assert_equivalent!(world.color_at(Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.)), 4), Color::new(0.5, 0.2, 0.1));
+    }
+}